@@ -0,0 +1,213 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, FixedOffset, Utc};
+use comannds::config::DaemonConfig;
+use comannds::context::Context;
+use comannds::domains::job_locks::{LockMode, LockOptions};
+use comannds::domains::job_runs::Summary;
+use comannds::domains::notifications::schedule::jst;
+use comannds::locale::Locale;
+use comannds::shutdown::Shutdown;
+use cron::Schedule;
+use futures::stream::FuturesUnordered;
+use futures::StreamExt;
+use std::collections::HashSet;
+use std::str::FromStr;
+use tokio::signal::unix::{signal, SignalKind};
+
+use crate::jobs;
+
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub enum JobKind {
+    UpdateAllWishlist,
+    SnapEbooks,
+    SendNotification,
+}
+
+#[derive(Debug)]
+struct Job {
+    kind: JobKind,
+    schedule: Schedule,
+}
+
+impl Job {
+    fn new(kind: JobKind, expr: &str) -> Result<Job> {
+        let schedule = Schedule::from_str(expr)
+            .map_err(|e| anyhow!("invalid cron for {:?}: {} ({})", kind, expr, e))?;
+        Ok(Job { kind, schedule })
+    }
+
+    fn next_after(&self, now: &DateTime<FixedOffset>) -> Option<DateTime<FixedOffset>> {
+        self.schedule.after(now).next()
+    }
+
+    async fn run(
+        &self,
+        ctx: &Context<'_>,
+        shutdown: &Shutdown,
+        lock: &LockOptions,
+    ) -> Result<Option<Summary>> {
+        match self.kind {
+            JobKind::UpdateAllWishlist => jobs::update_all_wishlist(ctx, shutdown, lock).await,
+            JobKind::SnapEbooks => jobs::snap_ebooks(ctx, shutdown, lock).await,
+            JobKind::SendNotification => jobs::send_notification(ctx, shutdown, lock).await,
+        }
+    }
+}
+
+fn jobs_from(config: &DaemonConfig) -> Result<Vec<Job>> {
+    Ok(vec![
+        Job::new(JobKind::UpdateAllWishlist, config.wish_list_cron.as_str())?,
+        Job::new(JobKind::SnapEbooks, config.ebook_cron.as_str())?,
+        Job::new(JobKind::SendNotification, config.notification_cron.as_str())?,
+    ])
+}
+
+/// cron の書式が正しいかだけ確かめる
+pub fn validate(config: &DaemonConfig) -> Result<()> {
+    jobs_from(config).map(|_| ())
+}
+
+/// 次に動かすジョブ。同じ時刻に動くものはまとめて返す
+fn next_run(
+    jobs: &[Job],
+    now: &DateTime<FixedOffset>,
+) -> Option<(Vec<usize>, DateTime<FixedOffset>)> {
+    let next = jobs
+        .iter()
+        .map(|job| job.next_after(now))
+        .collect::<Vec<_>>();
+    let at = next.iter().flatten().min().copied()?;
+    let due = next
+        .iter()
+        .enumerate()
+        .filter(|(_, x)| **x == Some(at))
+        .map(|(i, _)| i)
+        .collect();
+    Some((due, at))
+}
+
+fn listen_shutdown(locale: Locale) -> Result<Shutdown> {
+    let (sender, shutdown) = Shutdown::new();
+    let mut terminate = signal(SignalKind::terminate())?;
+    tokio::spawn(async move {
+        tokio::select! {
+            _ = terminate.recv() => {},
+            _ = tokio::signal::ctrl_c() => {},
+        }
+        info!("{}", locale.shutdown_requested());
+        sender.send(true).ok();
+    });
+    Ok(shutdown)
+}
+
+fn log_result(kind: JobKind, result: Result<Option<Summary>>, locale: Locale) {
+    match result {
+        Ok(None) => info!("{}", locale.skipped(format!("{:?}", kind))),
+        Ok(Some(summary)) => info!(
+            "{} ({})",
+            locale.finished(format!("{:?}", kind)),
+            locale.job_summary(summary.succeeded, summary.failed)
+        ),
+        Err(e) => error!("{:?} : {}", kind, e),
+    }
+}
+
+pub async fn run(ctx: &Context<'_>) -> Result<()> {
+    let config = ctx.config;
+    let jobs = jobs_from(&config.daemon)?;
+    let locale = config.locale;
+    let mut shutdown = listen_shutdown(locale)?;
+    // 別ホストの daemon や手動実行と重なったら、その回は見送る
+    let lock = LockOptions {
+        mode: LockMode::Skip,
+        ttl: config.daemon.lock_ttl,
+    };
+
+    let mut running = HashSet::new();
+    let mut in_flight = FuturesUnordered::new();
+    let mut now = Utc::now().with_timezone(&jst());
+    loop {
+        let (due, at) = next_run(&jobs, &now).ok_or(anyhow!("no jobs are scheduled"))?;
+        let wait = (at - Utc::now().with_timezone(&jst()))
+            .to_std()
+            .unwrap_or_default();
+        tokio::select! {
+            _ = tokio::time::sleep(wait) => {
+                now = at;
+                for job in due.into_iter().map(|i| &jobs[i]) {
+                    // 同じジョブは前回が終わるまで重ねて動かさない
+                    if !running.insert(job.kind) {
+                        warn!("{}", locale.still_running(format!("{:?}", job.kind)));
+                        continue;
+                    }
+                    info!("{}", locale.started(format!("{:?}", job.kind)));
+                    let shutdown = shutdown.clone();
+                    let lock = &lock;
+                    in_flight.push(async move { (job.kind, job.run(ctx, &shutdown, lock).await) });
+                }
+            }
+            Some((kind, result)) = in_flight.next(), if !in_flight.is_empty() => {
+                running.remove(&kind);
+                log_result(kind, result, locale);
+            }
+            _ = shutdown.requested() => break,
+        }
+    }
+
+    while let Some((kind, result)) = in_flight.next().await {
+        log_result(kind, result, locale);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_jobs() {
+        assert_eq!(jobs_from(&DaemonConfig::default()).unwrap().len(), 3);
+
+        let broken = DaemonConfig {
+            ebook_cron: "every day".to_string(),
+            ..DaemonConfig::default()
+        };
+        assert!(jobs_from(&broken).is_err());
+    }
+
+    #[test]
+    fn test_next_run() {
+        let jobs = jobs_from(&DaemonConfig::default()).unwrap();
+        let now = jst().with_ymd_and_hms(2023, 4, 1, 6, 30, 0).unwrap();
+
+        let (due, at) = next_run(&jobs, &now).unwrap();
+        assert_eq!(due, vec![1]);
+        assert_eq!(jobs[1].kind, JobKind::SnapEbooks);
+        assert_eq!(at, jst().with_ymd_and_hms(2023, 4, 1, 7, 0, 0).unwrap());
+
+        let (due, at) = next_run(&jobs, &at).unwrap();
+        assert_eq!(due, vec![2]);
+        assert_eq!(jobs[2].kind, JobKind::SendNotification);
+        assert_eq!(at, jst().with_ymd_and_hms(2023, 4, 1, 7, 30, 0).unwrap());
+    }
+
+    #[test]
+    fn test_next_run_same_time() {
+        let config = DaemonConfig {
+            ebook_cron: "0 0 7 * * *".to_string(),
+            notification_cron: "0 0 7 * * *".to_string(),
+            ..DaemonConfig::default()
+        };
+        let jobs = jobs_from(&config).unwrap();
+        let now = jst().with_ymd_and_hms(2023, 4, 1, 6, 30, 0).unwrap();
+
+        // 同じ時刻のジョブはどちらも飛ばさずに動かす
+        let (due, at) = next_run(&jobs, &now).unwrap();
+        assert_eq!(due, vec![1, 2]);
+        assert_eq!(at, jst().with_ymd_and_hms(2023, 4, 1, 7, 0, 0).unwrap());
+
+        let (_, next) = next_run(&jobs, &at).unwrap();
+        assert!(next > at);
+    }
+}
@@ -0,0 +1,86 @@
+use anyhow::Result;
+use comannds::context::Context;
+use comannds::domains::ebooks;
+use comannds::domains::job_locks::{self, LockOptions};
+use comannds::domains::job_runs::{self, Summary};
+use comannds::domains::notifications;
+use comannds::domains::rules;
+use comannds::domains::wish_lists::services;
+use comannds::shutdown::Shutdown;
+use db_client::prisma::PrismaClient;
+use queries::rules::select_all_rules;
+use queries::wish_list::select_all_with_snapshot;
+use std::future::Future;
+
+/// 同じジョブが重ならないようロックを取り、実行結果を JobRun に残す。
+/// LockMode::Skip で見送ったときは None
+async fn locked<F>(
+    client: &PrismaClient,
+    job: &str,
+    lock: &LockOptions,
+    run: F,
+) -> Result<Option<Summary>>
+where
+    F: Future<Output = Result<Summary>>,
+{
+    let Some(owner) = job_locks::acquire(client, job, lock).await? else {
+        return Ok(None);
+    };
+    // ロックを奪われて打ち切られた実行も失敗として残るよう、記録は hold の外側で行う
+    let held = job_locks::hold(client, job, owner.as_str(), lock, run);
+    let result = job_runs::record(client, job, held).await;
+    job_locks::repositories::release(client, job, owner.as_str()).await?;
+    result.map(Some)
+}
+
+pub async fn update_all_wishlist(
+    ctx: &Context<'_>,
+    shutdown: &Shutdown,
+    lock: &LockOptions,
+) -> Result<Option<Summary>> {
+    let run = services::update_all_wish_list(ctx, shutdown);
+    locked(ctx.client().await?, "UpdateAllWishlist", lock, run).await
+}
+
+pub async fn snap_ebooks(
+    ctx: &Context<'_>,
+    shutdown: &Shutdown,
+    lock: &LockOptions,
+) -> Result<Option<Summary>> {
+    let run = ebooks::snap_all_ebook(ctx, shutdown);
+    locked(ctx.client().await?, "SnapEbooks", lock, run).await
+}
+
+pub async fn send_notification(
+    ctx: &Context<'_>,
+    shutdown: &Shutdown,
+    lock: &LockOptions,
+) -> Result<Option<Summary>> {
+    locked(
+        ctx.client().await?,
+        "SendNotification",
+        lock,
+        notify_all(ctx, shutdown),
+    )
+    .await
+}
+
+async fn notify_all(ctx: &Context<'_>, shutdown: &Shutdown) -> Result<Summary> {
+    let client = ctx.client().await?;
+    let data = select_all_with_snapshot(client).await?;
+    let rules = rules::from_data(&select_all_rules(client).await?);
+    let mut results = vec![];
+    for d in data.iter().take_while(|_| !shutdown.is_requested()) {
+        let result = async {
+            notifications::notify(ctx, d).await?;
+            notifications::notify_rules(ctx, d, &rules).await
+        };
+        results.push(result.await);
+    }
+    let summary = Summary::from_results(results);
+    // 通知の結果は送れなくても返す
+    if let Err(e) = notifications::deliver_digest(ctx).await {
+        error!("can not deliver digest : {}", e);
+    }
+    Ok(summary)
+}
@@ -1,68 +1,637 @@
 #[macro_use]
 extern crate log;
 
-use clap::{Parser, Subcommand};
+mod daemon;
+mod jobs;
+
+use anyhow::Result;
+use chrono::{NaiveDate, TimeZone};
+use clap::{Parser, Subcommand, ValueEnum};
 use dotenv::dotenv;
+use std::path::{Path, PathBuf};
 
+use comannds::config::Config;
+use comannds::context::{Context, Database, Scraper};
+use comannds::domains::ebook_snapshots::history;
 use comannds::domains::ebooks;
-use comannds::domains::notifications;
+use comannds::domains::exports;
+use comannds::domains::imports;
+use comannds::domains::job_locks::{self, LockMode, LockOptions};
+use comannds::domains::job_runs;
+use comannds::domains::notifications::schedule::jst;
+use comannds::domains::notifications::templates::Templates;
+use comannds::domains::notifications::{self, Discord};
+use comannds::domains::rules;
 use comannds::domains::wish_lists::services;
+use comannds::shutdown::Shutdown;
+use db_client::prisma::PrismaClient;
+use queries::rules::select_all_rules;
 use queries::wish_list::select_all_with_snapshot;
 
-use crate::Commands::*;
+use crate::ContextCommands::*;
 
 #[derive(Parser)]
 #[clap(author, version, about, long_about = None)]
 struct Args {
     #[clap(subcommand)]
     command: Commands,
+    /// wait until other runs of the same job release the lock
+    #[clap(long, global = true, conflicts_with = "skip_if_locked")]
+    wait: bool,
+    /// exit quietly when other runs of the same job hold the lock
+    #[clap(long, global = true)]
+    skip_if_locked: bool,
+    /// seconds after which a lock not renewed is taken over (default: daemon.lock_ttl)
+    #[clap(long, global = true)]
+    lock_ttl: Option<i64>,
+    /// TOML config file (default: CONFIG_FILE or ./config.toml). env vars take precedence
+    #[clap(long, global = true)]
+    config: Option<PathBuf>,
+}
+
+impl Args {
+    fn lock(&self, config: &Config) -> LockOptions {
+        let mode = match (self.wait, self.skip_if_locked) {
+            (true, _) => LockMode::Wait,
+            (_, true) => LockMode::Skip,
+            _ => LockMode::Fail,
+        };
+        LockOptions {
+            mode,
+            ttl: self.lock_ttl.unwrap_or(config.daemon.lock_ttl),
+        }
+    }
 }
 
 #[derive(Subcommand, Debug)]
 enum Commands {
+    #[clap(flatten)]
+    Context(ContextCommands),
+    /// render notification templates with sample data
+    RenderTemplate {
+        /// directory of *.tera templates (default: NOTIFICATION_TEMPLATE_DIR)
+        #[clap(long)]
+        dir: Option<PathBuf>,
+    },
+    /// inspect the loaded config
+    Config {
+        #[clap(subcommand)]
+        command: ConfigCommands,
+    },
+}
+
+/// DB やブラウザを使うので Context を作ってから実行するコマンド
+#[derive(Subcommand, Debug)]
+enum ContextCommands {
     /// update all wishlists.
     UpdateAllWishlist,
     /// send notification for all ebooks
-    SendNotification,
+    SendNotification {
+        /// print rendered messages instead of sending
+        #[clap(long)]
+        dry_run: bool,
+        /// output format of --dry-run
+        #[clap(long, value_enum, default_value_t = Format::Table)]
+        format: Format,
+    },
     /// snap ebook data
     SnapEbooks,
     /// exec all work flow
     AllFlow,
+    /// run jobs on cron schedules until SIGTERM
+    Daemon,
+    /// manage wishlists
+    Wishlist {
+        #[clap(subcommand)]
+        command: WishlistCommands,
+    },
+    /// manage ebooks watched outside of wishlists
+    Ebook {
+        #[clap(subcommand)]
+        command: EbookCommands,
+    },
+    /// snap a single ebook or wishlist for debugging
+    Snap {
+        #[clap(subcommand)]
+        command: SnapCommands,
+    },
+    /// show price history of an ebook
+    History {
+        /// ASIN, ISBN or product url
+        asin: String,
+        /// only snapshots on and after this date (JST, e.g. 2023-04-01)
+        #[clap(long)]
+        since: Option<NaiveDate>,
+        /// max width of the sparkline
+        #[clap(long, default_value_t = 60)]
+        width: usize,
+    },
+    /// export data for analysis or migration
+    Export {
+        /// file format
+        #[clap(long, value_enum, default_value_t = ExportFormat::Csv)]
+        format: ExportFormat,
+        /// comma separated entities to export (default: all)
+        #[clap(long, value_enum, value_delimiter = ',')]
+        entities: Vec<ExportEntity>,
+        /// directory to write files and manifest.json into
+        #[clap(long, default_value = "export")]
+        dir: PathBuf,
+    },
+    /// restore data from an export directory
+    Import {
+        /// directory written by export
+        archive: PathBuf,
+        /// only validate the archive
+        #[clap(long)]
+        dry_run: bool,
+    },
+    /// inspect job run history
+    Runs {
+        #[clap(subcommand)]
+        command: RunsCommands,
+    },
 }
 
-#[tokio::main]
-async fn main() {
-    dotenv().ok();
-    env_logger::init();
+#[derive(Subcommand, Debug)]
+enum ConfigCommands {
+    /// validate the config and print it with secrets redacted
+    Check,
+}
 
-    let args = Args::parse();
+#[derive(Subcommand, Debug)]
+enum WishlistCommands {
+    /// register a wishlist after checking it can be scraped
+    Add {
+        /// wishlist url or id
+        url_or_id: String,
+    },
+    /// unregister a wishlist
+    Remove {
+        /// wishlist url or id
+        url_or_id: String,
+    },
+    /// list registered wishlists
+    List,
+    /// report books added and removed recently and how long books are piled
+    Report {
+        /// days to look back
+        #[clap(long, default_value_t = 7)]
+        days: i64,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum EbookCommands {
+    /// watch an ebook directly
+    Add {
+        /// ASIN, ISBN or product url
+        asin: String,
+    },
+    /// stop watching an ebook directly
+    Remove {
+        /// ASIN, ISBN or product url
+        asin: String,
+    },
+    /// delete archived ebooks and their snapshots
+    Purge {
+        /// days to keep archived ebooks
+        #[clap(long, default_value_t = 30)]
+        grace_days: i64,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum SnapCommands {
+    /// snap an ebook and print it as JSON
+    Ebook {
+        /// ASIN, ISBN or product url
+        asin: String,
+        /// do not write the snapshot to the database
+        #[clap(long)]
+        no_save: bool,
+    },
+    /// snap a wishlist and print it as JSON
+    Wishlist {
+        /// wishlist url or id
+        url_or_id: String,
+        /// do not write the snapshot to the database
+        #[clap(long)]
+        no_save: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum RunsCommands {
+    /// list recent job runs
+    List {
+        /// filter by job name (e.g. SnapEbooks)
+        #[clap(long)]
+        job: Option<String>,
+        #[clap(long, default_value_t = 20)]
+        limit: i64,
+    },
+    /// show a job run with its errors
+    Show { id: String },
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Format {
+    Json,
+    Table,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum ExportFormat {
+    Csv,
+    Json,
+    Ndjson,
+    Parquet,
+}
+
+impl From<ExportFormat> for exports::Format {
+    fn from(format: ExportFormat) -> Self {
+        match format {
+            ExportFormat::Csv => exports::Format::Csv,
+            ExportFormat::Json => exports::Format::Json,
+            ExportFormat::Ndjson => exports::Format::Ndjson,
+            ExportFormat::Parquet => exports::Format::Parquet,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum ExportEntity {
+    Wishlists,
+    Ebooks,
+    Memberships,
+    Snapshots,
+}
+
+impl From<ExportEntity> for exports::Entity {
+    fn from(entity: ExportEntity) -> Self {
+        match entity {
+            ExportEntity::Wishlists => exports::Entity::Wishlists,
+            ExportEntity::Ebooks => exports::Entity::Ebooks,
+            ExportEntity::Memberships => exports::Entity::Memberships,
+            ExportEntity::Snapshots => exports::Entity::Snapshots,
+        }
+    }
+}
+
+/// run の前に一度繋いであるので、ここでは繋ぎ直さない
+async fn client<'a>(ctx: &Context<'a>) -> &'a PrismaClient {
+    ctx.client().await.expect("can not connect to database")
+}
+
+async fn export(ctx: &Context<'_>, format: ExportFormat, entities: &[ExportEntity], dir: &Path) {
+    let mut entities = entities
+        .iter()
+        .map(|entity| exports::Entity::from(*entity))
+        .collect::<Vec<_>>();
+    if entities.is_empty() {
+        entities = exports::Entity::ALL.to_vec();
+    }
+    entities.sort();
+    entities.dedup();
+    let manifest = exports::export(client(ctx).await, dir, format.into(), &entities)
+        .await
+        .expect("can not export");
+    for (entity, count) in manifest.counts {
+        println!("{}\t{}", entity.name(), count);
+    }
+}
 
-    info!("{:?} : start", args.command);
-    match args.command {
+async fn import(ctx: &Context<'_>, archive: &Path, dry_run: bool) {
+    let report = imports::import(client(ctx).await, archive, dry_run)
+        .await
+        .expect("can not import");
+    let locale = ctx.config.locale;
+    if dry_run {
+        println!("{}", locale.archive_valid(&archive.display().to_string()));
+        return;
+    }
+    println!("{}", locale.import_headers().join("\t"));
+    for (entity, counts) in report {
+        println!(
+            "{}\t{}\t{}\t{}",
+            entity.name(),
+            counts.inserted,
+            counts.updated,
+            counts.skipped
+        );
+    }
+}
+
+async fn preview_notification(ctx: &Context<'_>, format: Format) {
+    let client = client(ctx).await;
+    let data = select_all_with_snapshot(client).await.unwrap();
+    let rules = rules::from_data(&select_all_rules(client).await.unwrap());
+    let mut previews = vec![];
+    for d in data {
+        previews.extend(notifications::render(ctx.config, &d, &rules).unwrap());
+    }
+    match format {
+        Format::Json => println!("{}", serde_json::to_string_pretty(&previews).unwrap()),
+        Format::Table => {
+            for preview in previews {
+                println!("{}\n", preview.table());
+            }
+        }
+    }
+}
+
+async fn wishlist(ctx: &Context<'_>, command: &WishlistCommands) {
+    match command {
+        WishlistCommands::Add { url_or_id } => {
+            let snapshot = services::add_wish_list(ctx, url_or_id)
+                .await
+                .expect("can not add");
+            println!(
+                "{}\t{}\t{}",
+                snapshot.id,
+                snapshot.title,
+                snapshot.items.len()
+            );
+        }
+        WishlistCommands::Remove { url_or_id } => {
+            if !services::remove_wish_list(ctx, url_or_id)
+                .await
+                .expect("can not remove")
+            {
+                println!("{}", ctx.config.locale.not_registered(url_or_id));
+            }
+        }
+        WishlistCommands::List => {
+            for list in services::list_wish_lists(client(ctx).await)
+                .await
+                .expect("can not list")
+            {
+                let scraped_at = jst().timestamp_opt(list.scraped_at, 0).unwrap();
+                println!(
+                    "{}\t{}\t{}\t{}",
+                    list.id,
+                    list.title,
+                    list.items,
+                    ctx.config.locale.datetime(&scraped_at)
+                );
+            }
+        }
+        WishlistCommands::Report { days } => {
+            let locale = ctx.config.locale;
+            for report in services::membership_report(client(ctx).await, *days)
+                .await
+                .expect("can not report")
+            {
+                println!("{} ({})", report.title, report.id);
+                println!("  {}", locale.added(report.added.len()));
+                report.added.iter().for_each(|x| println!("    + {}", x));
+                println!("  {}", locale.removed(report.removed.len()));
+                report.removed.iter().for_each(|x| println!("    - {}", x));
+                let oldest = report
+                    .oldest
+                    .as_ref()
+                    .map(|(title, days)| (title.as_str(), *days));
+                println!(
+                    "  {}",
+                    locale.piled(report.piled, report.average_days, oldest)
+                );
+            }
+        }
+    }
+}
+
+async fn ebook(ctx: &Context<'_>, command: &EbookCommands) {
+    match command {
+        EbookCommands::Add { asin } => {
+            let asin = ebooks::add_ebook(ctx, asin).await.expect("can not add");
+            println!("{}", asin);
+        }
+        EbookCommands::Remove { asin } => {
+            if !ebooks::remove_ebook(client(ctx).await, asin)
+                .await
+                .expect("can not remove")
+            {
+                println!("{}", ctx.config.locale.not_watched(asin));
+            }
+        }
+        EbookCommands::Purge { grace_days } => {
+            let (ebooks, snapshots) =
+                ebooks::purge_archived_ebooks(client(ctx).await, ctx.config, *grace_days)
+                    .await
+                    .expect("can not purge");
+            println!("{}\t{}", ebooks, snapshots);
+        }
+    }
+}
+
+async fn snap(ctx: &Context<'_>, command: &SnapCommands) {
+    let json = match command {
+        SnapCommands::Ebook { asin, no_save } => {
+            let snapshot = ebooks::snap_single_ebook(ctx, asin, !no_save)
+                .await
+                .expect("can not snap");
+            serde_json::to_string_pretty(&snapshot)
+        }
+        SnapCommands::Wishlist { url_or_id, no_save } => {
+            let snapshot = services::snap_single_wish_list(ctx, url_or_id, !no_save)
+                .await
+                .expect("can not snap");
+            serde_json::to_string_pretty(&snapshot)
+        }
+    };
+    println!("{}", json.unwrap());
+}
+
+async fn price_history(ctx: &Context<'_>, asin: &str, since: Option<NaiveDate>, width: usize) {
+    let locale = ctx.config.locale;
+    let since = since.map(|date| {
+        jst()
+            .from_local_datetime(&date.and_hms_opt(0, 0, 0).unwrap())
+            .unwrap()
+            .timestamp()
+    });
+    let snapshots = ebooks::price_history(client(ctx).await, asin, since)
+        .await
+        .expect("can not load history");
+    let Some(summary) = history::summary(&snapshots, locale) else {
+        println!("{}", locale.no_snapshots(asin));
+        return;
+    };
+    println!("{}\n", history::table(&snapshots, locale));
+    let prices = snapshots
+        .iter()
+        .map(history::effective_price)
+        .collect::<Vec<_>>();
+    println!("{}", history::sparkline(&prices, width));
+    println!("{}", summary);
+}
+
+async fn runs(ctx: &Context<'_>, command: &RunsCommands) {
+    let locale = ctx.config.locale;
+    let datetime = |at: i64| locale.datetime(&jst().timestamp_opt(at, 0).unwrap());
+    match command {
+        RunsCommands::List { job, limit } => {
+            let runs =
+                job_runs::repositories::select_recent(client(ctx).await, job.clone(), *limit)
+                    .await
+                    .expect("can not list");
+            for run in runs {
+                println!(
+                    "{}\t{}\t{}\t{}\t{}\t{}",
+                    run.id,
+                    run.job,
+                    run.status,
+                    datetime(run.started_at),
+                    run.succeeded,
+                    run.failed
+                );
+            }
+        }
+        RunsCommands::Show { id } => {
+            let Some(run) = job_runs::repositories::find(client(ctx).await, id.clone())
+                .await
+                .expect("can not show")
+            else {
+                println!("{}", locale.not_found(id));
+                return;
+            };
+            let [id, job, status, started, finished, duration, succeeded, failed, error] =
+                locale.run_labels();
+            println!("{}: {}", id, run.id);
+            println!("{}: {}", job, run.job);
+            println!("{}: {}", status, run.status);
+            println!("{}: {}", started, datetime(run.started_at));
+            if let Some(finished_at) = run.finished_at {
+                println!("{}: {}", finished, datetime(finished_at));
+                println!("{}: {}s", duration, finished_at - run.started_at);
+            }
+            println!("{}: {}", succeeded, run.succeeded);
+            println!("{}: {}", failed, run.failed);
+            if let Some(message) = run.error {
+                println!("{}:\n{}", error, message);
+            }
+        }
+    }
+}
+
+async fn run(ctx: &Context<'_>, command: &ContextCommands, lock: &LockOptions) {
+    match command {
         UpdateAllWishlist => {
-            services::update_all_wish_list()
+            jobs::update_all_wishlist(ctx, &Shutdown::never(), lock)
                 .await
                 .expect("can not update");
         }
-        SendNotification => {
-            let data = select_all_with_snapshot().await.unwrap();
-            for d in data {
-                notifications::notify(&d).await.unwrap();
+        SendNotification { dry_run, format } => {
+            if *dry_run {
+                preview_notification(ctx, *format).await;
+            } else {
+                jobs::send_notification(ctx, &Shutdown::never(), lock)
+                    .await
+                    .expect("can not send");
             }
         }
         SnapEbooks => {
-            ebooks::snap_all_ebook().await.expect("can not snap");
+            jobs::snap_ebooks(ctx, &Shutdown::never(), lock)
+                .await
+                .expect("can not snap");
         }
         AllFlow => {
-            services::update_all_wish_list()
+            let shutdown = Shutdown::never();
+            let flow = async {
+                jobs::update_all_wishlist(ctx, &shutdown, lock).await?;
+                jobs::snap_ebooks(ctx, &shutdown, lock).await?;
+                jobs::send_notification(ctx, &shutdown, lock).await
+            };
+            job_locks::with_lock(client(ctx).await, "AllFlow", lock, flow)
                 .await
-                .expect("can not update");
-            ebooks::snap_all_ebook().await.expect("can not snap");
-            let data = select_all_with_snapshot().await.unwrap();
-            for d in data {
-                notifications::notify(&d).await.unwrap();
+                .expect("can not run all flow");
+        }
+        Daemon => {
+            daemon::run(ctx).await.expect("daemon stopped");
+        }
+        Wishlist { command } => {
+            wishlist(ctx, command).await;
+        }
+        Ebook { command } => {
+            ebook(ctx, command).await;
+        }
+        Snap { command } => {
+            snap(ctx, command).await;
+        }
+        History { asin, since, width } => {
+            price_history(ctx, asin, *since, *width).await;
+        }
+        Export {
+            format,
+            entities,
+            dir,
+        } => {
+            export(ctx, *format, entities, dir).await;
+        }
+        Import { archive, dry_run } => {
+            import(ctx, archive, *dry_run).await;
+        }
+        Runs { command } => {
+            runs(ctx, command).await;
+        }
+    }
+}
+
+fn check_config(config: &Config) -> Result<()> {
+    daemon::validate(&config.daemon)?;
+    for (key, value) in config.summary() {
+        println!("{}\t{}", key, value);
+    }
+    println!("{}", config.locale.config_valid());
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    dotenv().ok();
+    env_logger::init();
+
+    let args = Args::parse();
+
+    let config = Config::load(args.config.as_deref())?;
+    let locale = config.locale;
+    let lock = args.lock(&config);
+    info!("{}", locale.started(format!("{:?}", args.command)));
+    match &args.command {
+        Commands::RenderTemplate { dir } => {
+            let loaded;
+            let templates = match dir {
+                Some(dir) => {
+                    loaded = Templates::load(Some(dir), locale).expect("can not load templates");
+                    &loaded
+                }
+                None => &config.templates,
+            };
+            for (name, rendered) in templates.render_sample().expect("can not render") {
+                println!("{}: {}", name, rendered);
             }
         }
+        Commands::Config { command } => match command {
+            ConfigCommands::Check => check_config(&config)?,
+        },
+        Commands::Context(command) => {
+            // DB の接続とブラウザは 1 つだけ作ってすべての処理で使い回す
+            let database = Database::new(config.database.url.as_str());
+            database
+                .client()
+                .await
+                .expect("can not connect to database");
+            let scraper = Scraper::default();
+            let ctx = Context {
+                database: &database,
+                fetcher: &scraper,
+                notifier: &Discord,
+                config: &config,
+            };
+            run(&ctx, command, &lock).await;
+        }
     }
-    info!("{:?} : finish", args.command);
+    info!("{}", locale.finished(format!("{:?}", args.command)));
+    Ok(())
 }
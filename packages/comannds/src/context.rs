@@ -0,0 +1,217 @@
+use anyhow::Result;
+use db_client::prisma::{self, PrismaClient};
+use futures::future::BoxFuture;
+use headless_chrome::Browser;
+use once_cell::sync::OnceCell;
+use scraper::asin::Asin;
+use url::Url;
+use webhook::models::Message;
+
+use crate::config::Config;
+use crate::domains::ebook_snapshots::{self, EbookSnapshot};
+use crate::domains::ebooks;
+use crate::domains::wish_list_snapshot::WishListSnapshot;
+use crate::domains::wish_lists;
+
+/// Amazon のページを取ってくる。テストでは固定の値を返すものに差し替える
+pub trait Fetcher: Send + Sync {
+    fn ebook_snapshot(&self, asin: &str) -> Result<EbookSnapshot>;
+    fn ebook_title(&self, asin: &Asin) -> Result<String>;
+    fn wish_list_snapshot(&self, id: &str) -> Result<WishListSnapshot>;
+    /// 短縮 URL のリダイレクト先からウィッシュリストの ID を取り出す
+    fn resolve_short_link(&self, url: &Url) -> Result<String>;
+}
+
+/// webhook にメッセージを送る
+pub trait Notifier: Send + Sync {
+    fn send<'a>(&'a self, url: &'a str, message: &'a Message) -> BoxFuture<'a, Result<()>>;
+}
+
+/// ブラウザは最初に使うときに起動する。DB だけを触るコマンドでは起動しない
+#[derive(Default)]
+pub struct Scraper {
+    browser: OnceCell<Browser>,
+}
+
+impl Scraper {
+    fn browser(&self) -> Result<&Browser> {
+        self.browser.get_or_try_init(Browser::default)
+    }
+}
+
+impl Fetcher for Scraper {
+    fn ebook_snapshot(&self, asin: &str) -> Result<EbookSnapshot> {
+        ebook_snapshots::repositories::get(self.browser()?, asin)
+    }
+
+    fn ebook_title(&self, asin: &Asin) -> Result<String> {
+        ebooks::repositories::get_title(self.browser()?, asin)
+    }
+
+    fn wish_list_snapshot(&self, id: &str) -> Result<WishListSnapshot> {
+        wish_lists::repositories::get_wish_list_snapshot(self.browser()?, id)
+    }
+
+    fn resolve_short_link(&self, url: &Url) -> Result<String> {
+        wish_lists::repositories::resolve_short_link(self.browser()?, url)
+    }
+}
+
+/// DB には最初に使うときに繋ぐ。DB を触らないテストでは繋がない
+pub struct Database {
+    url: String,
+    client: tokio::sync::OnceCell<PrismaClient>,
+}
+
+impl Database {
+    pub fn new<T: Into<String>>(url: T) -> Database {
+        Database {
+            url: url.into(),
+            client: tokio::sync::OnceCell::new(),
+        }
+    }
+
+    pub async fn client(&self) -> Result<&PrismaClient> {
+        let client = self
+            .client
+            .get_or_try_init(|| prisma::new_client_with_url(self.url.as_str()))
+            .await?;
+        Ok(client)
+    }
+}
+
+/// backend-cli で一度だけ作り、各処理に渡す
+#[derive(Clone, Copy)]
+pub struct Context<'a> {
+    pub database: &'a Database,
+    pub fetcher: &'a dyn Fetcher,
+    pub notifier: &'a dyn Notifier,
+    pub config: &'a Config,
+}
+
+impl<'a> Context<'a> {
+    pub async fn client(&self) -> Result<&'a PrismaClient> {
+        self.database.client().await
+    }
+}
+
+#[cfg(test)]
+pub mod fakes {
+    use super::*;
+    use crate::config::Source;
+    use anyhow::anyhow;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    const TOML: &str = r#"
+[database]
+url = "postgresql://localhost:5432/crate"
+
+[discord]
+bot_name = "bot"
+avatar_url = "https://example.com/avatar.png"
+alert_chanel = "https://example.com/alert"
+sale_chanel = "https://example.com/sale"
+"#;
+
+    /// 環境変数や設定ファイルを読まない設定
+    pub fn config() -> Config {
+        Config::from_source(&Source::from_toml(TOML).unwrap()).unwrap()
+    }
+
+    /// テストごとの Context の中身。DB は使ったときだけ .env の DATABASE_URL に繋ぐ
+    pub struct TestContext<F: Fetcher> {
+        pub database: Database,
+        pub fetcher: F,
+        pub notifier: FakeNotifier,
+        pub config: Config,
+    }
+
+    impl<F: Fetcher> TestContext<F> {
+        pub fn new(fetcher: F) -> TestContext<F> {
+            dotenv::dotenv().ok();
+            let config = config();
+            let url = std::env::var("DATABASE_URL").unwrap_or_else(|_| config.database.url.clone());
+            TestContext {
+                database: Database::new(url),
+                fetcher,
+                notifier: FakeNotifier::default(),
+                config,
+            }
+        }
+
+        pub fn ctx(&self) -> Context<'_> {
+            Context {
+                database: &self.database,
+                fetcher: &self.fetcher,
+                notifier: &self.notifier,
+                config: &self.config,
+            }
+        }
+    }
+
+    /// 登録されていないものを取ろうとするとエラーにする
+    #[derive(Default)]
+    pub struct FakeFetcher {
+        pub ebooks: HashMap<String, EbookSnapshot>,
+        pub titles: HashMap<String, String>,
+        pub wish_lists: HashMap<String, WishListSnapshot>,
+        pub short_links: HashMap<String, String>,
+    }
+
+    impl Fetcher for FakeFetcher {
+        fn ebook_snapshot(&self, asin: &str) -> Result<EbookSnapshot> {
+            self.ebooks
+                .get(asin)
+                .cloned()
+                .ok_or(anyhow!("{} is not found", asin))
+        }
+
+        fn ebook_title(&self, asin: &Asin) -> Result<String> {
+            self.titles
+                .get(asin.as_str())
+                .cloned()
+                .ok_or(anyhow!("{} is not found", asin))
+        }
+
+        fn wish_list_snapshot(&self, id: &str) -> Result<WishListSnapshot> {
+            self.wish_lists
+                .get(id)
+                .cloned()
+                .ok_or(anyhow!("{} is not found", id))
+        }
+
+        fn resolve_short_link(&self, url: &Url) -> Result<String> {
+            self.short_links
+                .get(url.as_str())
+                .cloned()
+                .ok_or(anyhow!("{} is not found", url))
+        }
+    }
+
+    /// 送らずに宛先と JSON を貯めておく。failing の宛先にはエラーを返す
+    #[derive(Default)]
+    pub struct FakeNotifier {
+        pub sent: Mutex<Vec<(String, serde_json::Value)>>,
+        pub failing: Vec<String>,
+    }
+
+    impl FakeNotifier {
+        pub fn sent(&self) -> Vec<(String, serde_json::Value)> {
+            self.sent.lock().unwrap().clone()
+        }
+    }
+
+    impl Notifier for FakeNotifier {
+        fn send<'a>(&'a self, url: &'a str, message: &'a Message) -> BoxFuture<'a, Result<()>> {
+            Box::pin(async move {
+                if self.failing.iter().any(|x| x == url) {
+                    return Err(anyhow!("{} is unavailable", url));
+                }
+                let json = serde_json::to_value(message)?;
+                self.sent.lock().unwrap().push((url.to_string(), json));
+                Ok(())
+            })
+        }
+    }
+}
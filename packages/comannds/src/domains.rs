@@ -1,6 +1,11 @@
 pub mod ebook_snapshots;
 pub mod ebooks;
+pub mod exports;
+pub mod imports;
 pub mod item_metadata;
+pub mod job_locks;
+pub mod job_runs;
 pub mod notifications;
-mod wish_list_snapshot;
+pub mod rules;
+pub mod wish_list_snapshot;
 pub mod wish_lists;
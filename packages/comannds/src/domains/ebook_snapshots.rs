@@ -1,28 +1,29 @@
+pub mod history;
 pub mod repositories;
 
+use crate::context::Context;
 use crate::domains::notifications::send_alert_message;
-use anyhow::Result;
-use db_client::prisma::PrismaClient;
-use headless_chrome::Browser;
+use anyhow::{anyhow, Result};
 use once_cell::sync::Lazy;
 use regex::Regex;
+use serde::Serialize;
 use std::collections::HashSet;
 use url::Url;
 
-pub async fn snap_ebook(client: &PrismaClient, browser: &Browser, id: String) -> Result<()> {
-    let snapshot = match repositories::get(browser, id.as_str()) {
+pub async fn snap_ebook(ctx: &Context<'_>, id: String) -> Result<()> {
+    let snapshot = match ctx.fetcher.ebook_snapshot(id.as_str()) {
         Ok(s) => s,
         Err(e) => {
-            let msg = format!("error snap_ebook id: {},  error:{}", id, e.to_string());
-            send_alert_message(msg.clone()).await.unwrap();
-            panic!("{}", msg);
+            let msg = ctx.config.locale.snap_failed(id.as_str(), e);
+            send_alert_message(ctx, msg.clone()).await?;
+            return Err(anyhow!(msg));
         }
     };
-    repositories::insert(client, &snapshot).await?;
+    repositories::insert(ctx.client().await?, &snapshot).await?;
     Ok(())
 }
 
-#[derive(Debug, Eq, Ord, PartialEq, PartialOrd, Clone)]
+#[derive(Debug, Eq, Ord, PartialEq, PartialOrd, Clone, Serialize)]
 pub struct Payment {
     pub price: String,
     pub points: String,
@@ -60,7 +61,7 @@ impl Payment {
     }
 }
 
-#[derive(Debug, Eq, Ord, PartialEq, PartialOrd, Clone)]
+#[derive(Debug, Eq, Ord, PartialEq, PartialOrd, Clone, Serialize)]
 pub struct EbookSnapshot {
     pub ebook_id: String,
     pub scraped_at: i64,
@@ -72,18 +73,27 @@ pub struct EbookSnapshot {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use db_client::prisma;
-    use dotenv;
+    use crate::context::fakes::{FakeFetcher, TestContext};
+    use crate::context::Scraper;
 
     #[tokio::test]
     async fn it_works_snap_ebook() {
-        dotenv::dotenv().ok();
-        let client = prisma::new_client().await.unwrap();
-        let browser = Browser::default().unwrap();
+        let test = TestContext::new(Scraper::default());
+        let ctx = test.ctx();
 
-        snap_ebook(&client, &browser, String::from("B00XV8YCJI"))
-            .await
-            .unwrap();
+        snap_ebook(&ctx, String::from("B00XV8YCJI")).await.unwrap();
+        assert!(test.notifier.sent().is_empty());
+    }
+
+    #[tokio::test]
+    async fn it_works_snap_ebook_alert() {
+        let test = TestContext::new(FakeFetcher::default());
+        let ctx = test.ctx();
+
+        assert!(snap_ebook(&ctx, String::from("B00XV8YCJI")).await.is_err());
+        let sent = test.notifier.sent();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].0, test.config.discord.alert_chanel);
     }
 
     #[test]
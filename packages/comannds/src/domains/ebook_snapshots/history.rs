@@ -0,0 +1,119 @@
+use crate::domains::notifications::schedule::jst;
+use crate::locale::Locale;
+use chrono::TimeZone;
+use db_client::prisma::ebook_snapshot::Data as EBookSnapShotData;
+
+const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// ポイント分を差し引いた実際の支払額
+pub fn effective_price(snapshot: &EBookSnapShotData) -> f64 {
+    snapshot.price - snapshot.points
+}
+
+/// width 個を超える分は隣り合うものを平均してまとめる
+fn downsample(values: &[f64], width: usize) -> Vec<f64> {
+    if width == 0 || values.len() <= width {
+        return values.to_vec();
+    }
+    let size = (values.len() + width - 1) / width;
+    values
+        .chunks(size)
+        .map(|chunk| chunk.iter().sum::<f64>() / chunk.len() as f64)
+        .collect()
+}
+
+pub fn sparkline(values: &[f64], width: usize) -> String {
+    let values = downsample(values, width);
+    let lowest = values.iter().cloned().fold(f64::INFINITY, f64::min);
+    let highest = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let range = highest - lowest;
+    values
+        .iter()
+        .map(|value| {
+            if range <= f64::EPSILON {
+                return BARS[BARS.len() / 2];
+            }
+            let i = ((value - lowest) / range * (BARS.len() - 1) as f64).round();
+            BARS[i as usize]
+        })
+        .collect()
+}
+
+pub fn table(snapshots: &[EBookSnapShotData], locale: Locale) -> String {
+    let header = locale.history_headers().join("\t");
+    let rows = snapshots.iter().map(|snapshot| {
+        let scraped_at = jst().timestamp_opt(snapshot.scraped_at, 0).unwrap();
+        format!(
+            "{}\t{}\t{:.1}%\t{:.1}%\t{}",
+            locale.datetime(&scraped_at),
+            locale.yen(snapshot.price),
+            snapshot.discount_rate.unwrap_or(0.0),
+            snapshot.points_rate,
+            locale.yen(effective_price(snapshot))
+        )
+    });
+    std::iter::once(header)
+        .chain(rows)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// 最安・最高・現在の実質価格。履歴がなければ None
+pub fn summary(snapshots: &[EBookSnapShotData], locale: Locale) -> Option<String> {
+    let prices = snapshots.iter().map(effective_price).collect::<Vec<_>>();
+    let latest = *prices.last()?;
+    let lowest = prices.iter().cloned().fold(f64::INFINITY, f64::min);
+    let highest = prices.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    Some(locale.price_range(lowest, highest, latest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(scraped_at: i64, price: f64, points: f64) -> EBookSnapShotData {
+        EBookSnapShotData {
+            id: scraped_at.to_string(),
+            ebook: None,
+            ebook_id: "B09RQGMYKZ".to_string(),
+            scraped_at,
+            thumbnail_url: String::new(),
+            price,
+            discount: None,
+            discount_rate: Some(10.0),
+            points,
+            points_rate: points / price * 100.0,
+        }
+    }
+
+    #[test]
+    fn test_sparkline() {
+        assert_eq!(sparkline(&[100.0, 200.0, 800.0, 450.0], 0), "▁▂█▅");
+        assert_eq!(sparkline(&[500.0, 500.0], 0), "▅▅");
+        assert_eq!(sparkline(&[], 0), "");
+        // 2 つずつ平均される
+        assert_eq!(sparkline(&[100.0, 100.0, 800.0, 800.0, 450.0], 3), "▁█▅");
+    }
+
+    #[test]
+    fn test_table() {
+        let snapshots = vec![
+            snapshot(1677672300, 1000.0, 100.0),
+            snapshot(1677758700, 800.0, 400.0),
+        ];
+        assert_eq!(
+            table(&snapshots, Locale::Ja),
+            [
+                "取得日時\t金額\t値引き率\tポイント還元率\t実質価格",
+                "2023/03/01 21:05:00 +09:00\t1,000円\t10.0%\t10.0%\t900円",
+                "2023/03/02 21:05:00 +09:00\t800円\t10.0%\t50.0%\t400円",
+            ]
+            .join("\n")
+        );
+        assert_eq!(
+            summary(&snapshots, Locale::En).unwrap(),
+            "lowest ¥400 / highest ¥900 / latest ¥400"
+        );
+        assert_eq!(summary(&[], Locale::En), None);
+    }
+}
@@ -1,16 +1,18 @@
 use crate::domains::ebook_snapshots::{EbookSnapshot, Payment};
 use anyhow::anyhow;
 use chrono::Utc;
+use db_client::prisma::ebook_snapshot::Data as EBookSnapShotData;
 use db_client::prisma::{ebook, ebook_snapshot, PrismaClient};
 use headless_chrome::Browser;
 use math::round;
+use prisma_client_rust::Direction::Asc;
+use scraper::asin::Asin;
 use scraper::dict::from;
 use url::Url;
 
 pub fn create_url(id: &str) -> anyhow::Result<Url> {
-    let url = Url::parse("https://www.amazon.co.jp/dp/")?;
-    let joined = url.join(id)?;
-    Ok(joined)
+    let asin = Asin::parse(id)?;
+    Ok(asin.url())
 }
 
 pub fn get(browser: &Browser, id: &str) -> anyhow::Result<EbookSnapshot> {
@@ -82,6 +84,25 @@ pub async fn insert(client: &PrismaClient, ebook_snapshot: &EbookSnapshot) -> an
     Ok(())
 }
 
+/// 古い順。since を渡すとそれ以降のものだけ
+pub async fn select_history(
+    client: &PrismaClient,
+    ebook_id: &str,
+    since: Option<i64>,
+) -> anyhow::Result<Vec<EBookSnapShotData>> {
+    let mut params = vec![ebook_snapshot::ebook_id::equals(ebook_id.to_string())];
+    if let Some(since) = since {
+        params.push(ebook_snapshot::scraped_at::gte(since));
+    }
+    let snapshots = client
+        .ebook_snapshot()
+        .find_many(params)
+        .order_by(ebook_snapshot::scraped_at::order(Asc))
+        .exec()
+        .await?;
+    Ok(snapshots)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -119,6 +140,30 @@ mod tests {
         insert(&client, &expected).await.unwrap()
     }
 
+    #[tokio::test]
+    async fn test_select_history() {
+        dotenv::dotenv().ok();
+
+        let client = prisma::new_client().await.unwrap();
+        let actual = select_history(&client, "B09RQGMYKZ", Some(1))
+            .await
+            .unwrap();
+
+        assert!(actual.iter().all(|s| s.scraped_at >= 1));
+        assert!(actual
+            .windows(2)
+            .all(|w| w[0].scraped_at <= w[1].scraped_at));
+    }
+
+    #[test]
+    fn test_create_url() {
+        assert_eq!(
+            create_url("b09rqgmykz").unwrap(),
+            Url::parse("https://www.amazon.co.jp/dp/B09RQGMYKZ").unwrap()
+        );
+        assert!(create_url("../hz/wishlist").is_err());
+    }
+
     #[test]
     fn test_get() {
         let browser = Browser::default().unwrap();
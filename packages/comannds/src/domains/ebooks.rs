@@ -1,24 +1,135 @@
-mod repositories;
+pub(crate) mod repositories;
 
-use crate::domains::ebook_snapshots::snap_ebook;
-use anyhow::Result;
-use db_client::prisma;
+use crate::config::Config;
+use crate::context::Context;
+use crate::domains::ebook_snapshots;
+use crate::domains::ebook_snapshots::{snap_ebook, EbookSnapshot};
+use crate::domains::job_runs::Summary;
+use crate::shutdown::Shutdown;
+use anyhow::{anyhow, Result};
+use chrono::{Duration, Utc};
+use db_client::prisma::ebook_snapshot::Data as EBookSnapShotData;
+use db_client::prisma::PrismaClient;
 use futures::stream;
 use futures::StreamExt;
-use headless_chrome::Browser;
+use scraper::asin::Asin;
+use url::Url;
 
-pub async fn snap_all_ebook() -> Result<()> {
-    let client = prisma::new_client().await?;
-    let lists = repositories::select_all(&client).await?;
-    let browser = Browser::default()?;
+/// 停止要求が来たら、取得中のものだけ終わらせて残りはスキップする。
+/// 取得済みのものは鮮度の範囲内なら飛ばすので、途中で止まっても再実行すれば続きから取れる
+pub async fn snap_all_ebook(ctx: &Context<'_>, shutdown: &Shutdown) -> Result<Summary> {
+    let (client, config) = (ctx.client().await?, ctx.config);
+    let locale = config.locale;
+    let ebooks = repositories::select_active(client).await?;
+    let orphans = repositories::orphans(&ebooks)
+        .iter()
+        .map(|ebook| ebook.id.clone())
+        .collect::<Vec<_>>();
+    let archived = repositories::archive(client, orphans.clone(), Utc::now().timestamp()).await?;
+    if archived > 0 {
+        info!("{}", locale.archived(archived));
+    }
 
-    let futures = lists
-        .into_iter()
-        .map(|ebook| snap_ebook(&client, &browser, ebook.id))
+    let active = ebooks
+        .iter()
+        .filter(|ebook| !orphans.contains(&ebook.id))
         .collect::<Vec<_>>();
-    let stream = stream::iter(futures).buffer_unordered(3);
-    stream.collect::<Vec<_>>().await;
-    Ok(())
+    let fresh_after = (Utc::now() - Duration::hours(config.scraper.freshness_hours)).timestamp();
+    let stale = repositories::stale_first(active.clone(), fresh_after);
+    if stale.len() < active.len() {
+        info!("{}", locale.fresh_skipped(active.len() - stale.len()));
+    }
+
+    let futures = stale
+        .into_iter()
+        .take_while(|_| !shutdown.is_requested())
+        .map(|ebook| async move {
+            tokio::time::sleep(config.scraper.request_interval()).await;
+            snap_ebook(ctx, ebook.id.clone()).await
+        });
+    let stream = stream::iter(futures).buffer_unordered(config.scraper.concurrency);
+    let results = stream.collect::<Vec<_>>().await;
+    Ok(Summary::from_results(results))
+}
+
+/// ASIN / ISBN / 商品ページの URL のどれでも受け付ける
+fn parse_asin(value: &str) -> Result<Asin> {
+    Asin::parse(value)
+        .or_else(|_| Asin::from_isbn(value))
+        .ok()
+        .or_else(|| {
+            Url::parse(value.trim())
+                .ok()
+                .and_then(|url| Asin::from_url(&url))
+        })
+        .ok_or(anyhow!("{} is not an ASIN, ISBN or product url", value))
+}
+
+/// 1 冊だけ取得する。save が false なら DB には書き込まない
+pub async fn snap_single_ebook(
+    ctx: &Context<'_>,
+    value: &str,
+    save: bool,
+) -> Result<EbookSnapshot> {
+    let asin = parse_asin(value)?;
+    let snapshot = ctx.fetcher.ebook_snapshot(asin.as_str())?;
+    if save {
+        let client = ctx.client().await?;
+        if !repositories::exists(client, &asin).await? {
+            return Err(anyhow!(
+                "{} is not registered, add it with `ebook add`",
+                asin
+            ));
+        }
+        ebook_snapshots::repositories::insert(client, &snapshot).await?;
+    }
+    Ok(snapshot)
+}
+
+/// since (unix time) 以降のスナップショットを古い順に返す
+pub async fn price_history(
+    client: &PrismaClient,
+    value: &str,
+    since: Option<i64>,
+) -> Result<Vec<EBookSnapShotData>> {
+    let asin = parse_asin(value)?;
+    ebook_snapshots::repositories::select_history(client, asin.as_str(), since).await
+}
+
+/// ウィッシュリストに入っていない本も直接監視する
+pub async fn add_ebook(ctx: &Context<'_>, value: &str) -> Result<Asin> {
+    let asin = parse_asin(value)?;
+
+    let title = ctx.fetcher.ebook_title(&asin)?;
+    let snapshot = ctx.fetcher.ebook_snapshot(asin.as_str())?;
+    let price = snapshot
+        .payment_ebook
+        .as_ref()
+        .ok_or(anyhow!("{} has no ebook edition", asin))?
+        .price
+        .parse::<f64>()?;
+
+    let client = ctx.client().await?;
+    repositories::watch(client, &asin, title, price, Utc::now().timestamp()).await?;
+    ebook_snapshots::repositories::insert(client, &snapshot).await?;
+    Ok(asin)
+}
+
+pub async fn remove_ebook(client: &PrismaClient, value: &str) -> Result<bool> {
+    let asin = parse_asin(value)?;
+    repositories::unwatch(client, &asin).await
+}
+
+/// アーカイブされてから grace_days 日以上経った本をスナップショットごと消す
+pub async fn purge_archived_ebooks(
+    client: &PrismaClient,
+    config: &Config,
+    grace_days: i64,
+) -> Result<(i64, i64)> {
+    let before = (Utc::now() - Duration::days(grace_days)).timestamp();
+    let (ebooks, snapshots) = repositories::purge_archived(client, before).await?;
+    info!("{}", config.locale.purged(ebooks, snapshots));
+    Ok((ebooks, snapshots))
 }
 
 #[cfg(test)]
@@ -26,11 +137,35 @@ mod tests {
     use super::*;
     use dotenv;
 
+    #[test]
+    fn test_parse_asin() {
+        let expected = Asin::parse("B09RQGMYKZ").unwrap();
+        assert_eq!(parse_asin("B09RQGMYKZ").unwrap(), expected);
+        assert_eq!(
+            parse_asin("https://www.amazon.co.jp/gp/product/B09RQGMYKZ").unwrap(),
+            expected
+        );
+        assert_eq!(
+            parse_asin("978-4-06-384375-0").unwrap(),
+            Asin::parse("4063843750").unwrap()
+        );
+        assert!(parse_asin("2BDAPI9RQ09E9").is_err());
+    }
+
     // #[tokio::test] // 必要な時だけ動かす
     // async fn it_works_snap_all_ebook() {
     //     dotenv::dotenv().ok();
+    //     let config = Config::load(None).unwrap();
+    //     let database = crate::context::Database::new(config.database.url.as_str());
+    //     let scraper = crate::context::Scraper::default();
+    //     let ctx = Context {
+    //         database: &database,
+    //         fetcher: &scraper,
+    //         notifier: &crate::domains::notifications::Discord,
+    //         config: &config,
+    //     };
     //
-    //     let actual = snap_all_ebook().await.unwrap();
-    //     assert_eq!(actual, ());
+    //     let actual = snap_all_ebook(&ctx, &Shutdown::never()).await.unwrap();
+    //     assert_eq!(actual.failed, 0);
     // }
 }
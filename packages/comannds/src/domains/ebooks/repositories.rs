@@ -1,26 +1,327 @@
 use anyhow::Result;
 use db_client::prisma::ebook::Data as EbookData;
-use db_client::prisma::PrismaClient;
+use db_client::prisma::{ebook, ebook_in_wish_list, ebook_snapshot, watched_ebook, PrismaClient};
+use headless_chrome::Browser;
+use prisma_client_rust::Direction::Desc;
+use scraper::asin::Asin;
 
-pub async fn select_all(client: &PrismaClient) -> Result<Vec<EbookData>> {
-    let ebooks = client.ebook().find_many(vec![]).exec().await?;
+pub async fn select_active(client: &PrismaClient) -> Result<Vec<EbookData>> {
+    let ebooks = client
+        .ebook()
+        .find_many(vec![ebook::archived_at::equals(None)])
+        .with(ebook::ebook_in_wish_list::fetch(vec![
+            ebook_in_wish_list::removed_at::equals(None),
+        ]))
+        .with(ebook::watched::fetch())
+        .with(
+            ebook::snapshots::fetch(vec![])
+                .order_by(ebook_snapshot::scraped_at::order(Desc))
+                .take(1),
+        )
+        .exec()
+        .await?;
     Ok(ebooks)
 }
 
+/// 最後にスナップショットを取った日時。一度も取っていなければ None
+fn last_scraped_at(ebook: &EbookData) -> Option<i64> {
+    ebook.snapshots.as_ref()?.first().map(|s| s.scraped_at)
+}
+
+/// fresh_after 以降に取得済みの本を除き、未取得のもの、古いものから順に並べる
+pub fn stale_first<'a>(ebooks: Vec<&'a EbookData>, fresh_after: i64) -> Vec<&'a EbookData> {
+    let mut stale = ebooks
+        .into_iter()
+        .filter(|ebook| last_scraped_at(ebook).map_or(true, |at| at < fresh_after))
+        .collect::<Vec<_>>();
+    stale.sort_by_key(|ebook| last_scraped_at(ebook));
+    stale
+}
+
+/// どのウィッシュリストにも入っておらず、直接監視もされていない本
+pub fn orphans(ebooks: &[EbookData]) -> Vec<&EbookData> {
+    ebooks
+        .iter()
+        .filter(|ebook| {
+            let in_wish_list = ebook
+                .ebook_in_wish_list
+                .as_ref()
+                .map(|x| !x.is_empty())
+                .unwrap_or(true);
+            let watched = ebook.watched.as_ref().map(|x| x.is_some()).unwrap_or(true);
+            !in_wish_list && !watched
+        })
+        .collect()
+}
+
+pub async fn archive(client: &PrismaClient, ids: Vec<String>, archived_at: i64) -> Result<i64> {
+    if ids.is_empty() {
+        return Ok(0);
+    }
+    let count = client
+        .ebook()
+        .update_many(
+            vec![ebook::id::in_vec(ids)],
+            vec![ebook::archived_at::set(Some(archived_at))],
+        )
+        .exec()
+        .await?;
+    Ok(count)
+}
+
+/// archived_at が before 以前の本をスナップショットごと消す
+pub async fn purge_archived(client: &PrismaClient, before: i64) -> Result<(i64, i64)> {
+    let ids = client
+        .ebook()
+        .find_many(vec![ebook::archived_at::lte(before)])
+        .exec()
+        .await?
+        .into_iter()
+        .map(|ebook| ebook.id)
+        .collect::<Vec<_>>();
+    if ids.is_empty() {
+        return Ok((0, 0));
+    }
+
+    // EbookSnapshot は cascade されないので先に消す
+    let (snapshots, _, ebooks) = client
+        ._batch((
+            client
+                .ebook_snapshot()
+                .delete_many(vec![ebook_snapshot::ebook_id::in_vec(ids.clone())]),
+            // 外した後の EbookInWishList も残っていると消せない
+            client
+                .ebook_in_wish_list()
+                .delete_many(vec![ebook_in_wish_list::ebook_id::in_vec(ids.clone())]),
+            client.ebook().delete_many(vec![ebook::id::in_vec(ids)]),
+        ))
+        .await?;
+    Ok((ebooks, snapshots))
+}
+
+pub async fn exists(client: &PrismaClient, asin: &Asin) -> Result<bool> {
+    let ebook = client
+        .ebook()
+        .find_unique(ebook::id::equals(asin.to_string()))
+        .exec()
+        .await?;
+    Ok(ebook.is_some())
+}
+
+pub fn get_title(browser: &Browser, asin: &Asin) -> Result<String> {
+    let tab = browser.new_tab()?;
+    tab.navigate_to(asin.url().as_str())?;
+    let title = tab.wait_for_element("#productTitle")?.get_inner_text()?;
+    tab.close(true)?;
+    Ok(title.trim().to_string())
+}
+
+pub async fn watch(
+    client: &PrismaClient,
+    asin: &Asin,
+    title: String,
+    price: f64,
+    watched_at: i64,
+) -> Result<()> {
+    let id = asin.to_string();
+    client
+        ._batch((
+            client.ebook().upsert(
+                ebook::id::equals(id.clone()),
+                ebook::create(id.clone(), asin.url().to_string(), title, price, vec![]),
+                vec![ebook::archived_at::set(None)],
+            ),
+            client.watched_ebook().upsert(
+                watched_ebook::ebook_id::equals(id.clone()),
+                (ebook::id::equals(id), watched_at, vec![]),
+                vec![],
+            ),
+        ))
+        .await?;
+    Ok(())
+}
+
+pub async fn unwatch(client: &PrismaClient, asin: &Asin) -> Result<bool> {
+    let deleted = client
+        .watched_ebook()
+        .delete_many(vec![watched_ebook::ebook_id::equals(asin.to_string())])
+        .exec()
+        .await?;
+    Ok(deleted > 0)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use db_client::prisma;
+    use db_client::prisma::ebook_in_wish_list::Data as EbookInWishListData;
+    use db_client::prisma::ebook_snapshot::Data as EBookSnapShotData;
+    use db_client::prisma::watched_ebook::Data as WatchedEbookData;
+    use db_client::prisma::wish_list;
 
     #[tokio::test]
-    async fn test_select_all() {
+    async fn test_select_active() {
         dotenv::dotenv().ok();
 
         let client = prisma::new_client().await.unwrap();
-        let actual = select_all(&client).await.unwrap();
+        let actual = select_active(&client).await.unwrap();
 
         assert!(actual.len() > 0)
     }
-}
 
-pub mod db {}
+    fn ebook_helper(
+        id: &str,
+        in_wish_list: bool,
+        watched: bool,
+        last_scraped_at: Option<i64>,
+    ) -> EbookData {
+        EbookData {
+            id: id.to_string(),
+            url: format!("https://www.amazon.co.jp/dp/{}", id),
+            title: "title".to_string(),
+            price: 100.0,
+            snapshots: Some(
+                last_scraped_at
+                    .into_iter()
+                    .map(|scraped_at| EBookSnapShotData {
+                        id: format!("{}-{}", id, scraped_at),
+                        ebook: None,
+                        ebook_id: id.to_string(),
+                        scraped_at,
+                        thumbnail_url: String::new(),
+                        price: 100.0,
+                        discount: None,
+                        discount_rate: None,
+                        points: 0.0,
+                        points_rate: 0.0,
+                    })
+                    .collect(),
+            ),
+            ebook_in_wish_list: Some(if in_wish_list {
+                vec![EbookInWishListData {
+                    id: id.to_string(),
+                    added_at: 0,
+                    removed_at: None,
+                    wish_list: None,
+                    wish_list_id: "2BDAPI9RQ09E9".to_string(),
+                    ebook: None,
+                    ebook_id: id.to_string(),
+                }]
+            } else {
+                vec![]
+            }),
+            watched: Some(watched.then(|| {
+                Box::new(WatchedEbookData {
+                    ebook: None,
+                    ebook_id: id.to_string(),
+                    watched_at: 0,
+                })
+            })),
+            archived_at: None,
+        }
+    }
+
+    #[test]
+    fn test_orphans() {
+        let ebooks = vec![
+            ebook_helper("B000000001", true, false, None),
+            ebook_helper("B000000002", false, true, None),
+            ebook_helper("B000000003", true, true, None),
+            ebook_helper("B000000004", false, false, None),
+        ];
+
+        let actual = orphans(&ebooks)
+            .iter()
+            .map(|ebook| ebook.id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(actual, vec!["B000000004"]);
+    }
+
+    #[test]
+    fn test_stale_first() {
+        let ebooks = vec![
+            ebook_helper("B000000001", true, false, Some(300)),
+            ebook_helper("B000000002", true, false, Some(100)),
+            ebook_helper("B000000003", true, false, None),
+            ebook_helper("B000000004", true, false, Some(200)),
+            ebook_helper("B000000005", true, false, Some(250)),
+        ];
+
+        let actual = stale_first(ebooks.iter().collect(), 250)
+            .iter()
+            .map(|ebook| ebook.id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(actual, vec!["B000000003", "B000000002", "B000000004"]);
+    }
+
+    #[tokio::test]
+    async fn test_watch_and_unwatch() {
+        dotenv::dotenv().ok();
+
+        let client = prisma::new_client().await.unwrap();
+        let asin = Asin::parse("B09RQGMYKZ").unwrap();
+
+        watch(&client, &asin, String::from("title"), 100.0, 0)
+            .await
+            .unwrap();
+        assert!(unwatch(&client, &asin).await.unwrap());
+        assert!(!unwatch(&client, &asin).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_purge_archived_with_removed_membership() {
+        dotenv::dotenv().ok();
+
+        let client = prisma::new_client().await.unwrap();
+        let wish_list_id = String::from("PURGETEST0001");
+        let ebook_id = String::from("B0PURGE001");
+        client
+            ._batch((
+                client.wish_list().upsert(
+                    wish_list::id::equals(wish_list_id.clone()),
+                    wish_list::create(
+                        wish_list_id.clone(),
+                        String::from("https://www.amazon.jp/hz/wishlist/ls/PURGETEST0001"),
+                        0,
+                        String::from("purge"),
+                        vec![],
+                    ),
+                    vec![],
+                ),
+                client.ebook().upsert(
+                    ebook::id::equals(ebook_id.clone()),
+                    ebook::create(
+                        ebook_id.clone(),
+                        format!("https://www.amazon.co.jp/dp/{}", ebook_id),
+                        String::from("title"),
+                        100.0,
+                        vec![ebook::archived_at::set(Some(1))],
+                    ),
+                    vec![ebook::archived_at::set(Some(1))],
+                ),
+                client
+                    .ebook_in_wish_list()
+                    .create_many(vec![ebook_in_wish_list::create(
+                        wish_list_id.clone(),
+                        ebook_id.clone(),
+                        0,
+                        vec![ebook_in_wish_list::removed_at::set(Some(1))],
+                    )]),
+            ))
+            .await
+            .unwrap();
+
+        let (ebooks, _) = purge_archived(&client, 1).await.unwrap();
+        assert!(ebooks >= 1);
+        assert!(!exists(&client, &Asin::parse(&ebook_id).unwrap())
+            .await
+            .unwrap());
+        let memberships = client
+            .ebook_in_wish_list()
+            .find_many(vec![ebook_in_wish_list::ebook_id::equals(ebook_id)])
+            .exec()
+            .await
+            .unwrap();
+        assert!(memberships.is_empty());
+    }
+}
@@ -0,0 +1,308 @@
+pub mod files;
+pub mod repositories;
+
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use db_client::prisma::ebook::Data as EbookData;
+use db_client::prisma::ebook_in_wish_list::Data as EbookInWishListData;
+use db_client::prisma::ebook_snapshot::Data as EBookSnapShotData;
+use db_client::prisma::wish_list::Data as WishListData;
+use db_client::prisma::PrismaClient;
+use parquet_derive::ParquetRecordWriter;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::future::Future;
+use std::path::Path;
+
+use self::files::RecordFile;
+
+/// 一度に DB から読み込む件数
+const PAGE_SIZE: i64 = 500;
+pub const MANIFEST: &str = "manifest.json";
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    Csv,
+    Json,
+    Ndjson,
+    Parquet,
+}
+
+impl Format {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Csv => "csv",
+            Format::Json => "json",
+            Format::Ndjson => "ndjson",
+            Format::Parquet => "parquet",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Entity {
+    Wishlists,
+    Ebooks,
+    Memberships,
+    Snapshots,
+}
+
+impl Entity {
+    /// 依存される側から順に並べてある
+    pub const ALL: [Entity; 4] = [
+        Entity::Wishlists,
+        Entity::Ebooks,
+        Entity::Memberships,
+        Entity::Snapshots,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Entity::Wishlists => "wishlists",
+            Entity::Ebooks => "ebooks",
+            Entity::Memberships => "memberships",
+            Entity::Snapshots => "snapshots",
+        }
+    }
+
+    pub fn file_name(&self, format: Format) -> String {
+        format!("{}.{}", self.name(), format.extension())
+    }
+}
+
+/// エクスポートしたディレクトリの中身
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub format: Format,
+    pub exported_at: i64,
+    pub counts: BTreeMap<Entity, usize>,
+}
+
+pub trait Record: Serialize + DeserializeOwned {
+    fn id(&self) -> &str;
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, ParquetRecordWriter)]
+pub struct WishListRecord {
+    pub id: String,
+    pub url: String,
+    pub title: String,
+    pub scraped_at: i64,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, ParquetRecordWriter)]
+pub struct EbookRecord {
+    pub id: String,
+    pub url: String,
+    pub title: String,
+    pub price: f64,
+    pub archived_at: Option<i64>,
+    /// ウィッシュリスト外で直接監視している場合の開始日時
+    pub watched_at: Option<i64>,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, ParquetRecordWriter)]
+pub struct MembershipRecord {
+    pub id: String,
+    pub wish_list_id: String,
+    pub ebook_id: String,
+    pub added_at: i64,
+    pub removed_at: Option<i64>,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, ParquetRecordWriter)]
+pub struct SnapshotRecord {
+    pub id: String,
+    pub ebook_id: String,
+    pub scraped_at: i64,
+    pub thumbnail_url: String,
+    pub price: f64,
+    pub discount: Option<f64>,
+    pub discount_rate: Option<f64>,
+    pub points: f64,
+    pub points_rate: f64,
+}
+
+impl From<WishListData> for WishListRecord {
+    fn from(data: WishListData) -> Self {
+        WishListRecord {
+            id: data.id,
+            url: data.url,
+            title: data.title,
+            scraped_at: data.scraped_at,
+        }
+    }
+}
+
+impl From<EbookData> for EbookRecord {
+    fn from(data: EbookData) -> Self {
+        let watched_at = data.watched.flatten().map(|watched| watched.watched_at);
+        EbookRecord {
+            id: data.id,
+            url: data.url,
+            title: data.title,
+            price: data.price,
+            archived_at: data.archived_at,
+            watched_at,
+        }
+    }
+}
+
+impl From<EbookInWishListData> for MembershipRecord {
+    fn from(data: EbookInWishListData) -> Self {
+        MembershipRecord {
+            id: data.id,
+            wish_list_id: data.wish_list_id,
+            ebook_id: data.ebook_id,
+            added_at: data.added_at,
+            removed_at: data.removed_at,
+        }
+    }
+}
+
+impl From<EBookSnapShotData> for SnapshotRecord {
+    fn from(data: EBookSnapShotData) -> Self {
+        SnapshotRecord {
+            id: data.id,
+            ebook_id: data.ebook_id,
+            scraped_at: data.scraped_at,
+            thumbnail_url: data.thumbnail_url,
+            price: data.price,
+            discount: data.discount,
+            discount_rate: data.discount_rate,
+            points: data.points,
+            points_rate: data.points_rate,
+        }
+    }
+}
+
+impl Record for WishListRecord {
+    fn id(&self) -> &str {
+        self.id.as_str()
+    }
+}
+
+impl Record for EbookRecord {
+    fn id(&self) -> &str {
+        self.id.as_str()
+    }
+}
+
+impl Record for MembershipRecord {
+    fn id(&self) -> &str {
+        self.id.as_str()
+    }
+}
+
+impl Record for SnapshotRecord {
+    fn id(&self) -> &str {
+        self.id.as_str()
+    }
+}
+
+/// id 順にページ単位で読み込みながら書き出す
+async fn export_pages<T, F, Fut>(path: &Path, format: Format, mut fetch: F) -> Result<usize>
+where
+    T: Record,
+    for<'a> &'a [T]: parquet::record::RecordWriter<T>,
+    F: FnMut(Option<String>, i64) -> Fut,
+    Fut: Future<Output = Result<Vec<T>>>,
+{
+    let mut file = RecordFile::create(path, format)?;
+    let mut after = None;
+    loop {
+        let page = fetch(after, PAGE_SIZE).await?;
+        file.write_page(&page)?;
+        match page.last() {
+            Some(last) if page.len() as i64 == PAGE_SIZE => after = Some(last.id().to_string()),
+            _ => break,
+        }
+    }
+    file.finish()
+}
+
+pub async fn export_entity(
+    client: &PrismaClient,
+    dir: &Path,
+    format: Format,
+    entity: Entity,
+) -> Result<usize> {
+    let path = dir.join(entity.file_name(format));
+    match entity {
+        Entity::Wishlists => {
+            export_pages(&path, format, |after, take| {
+                repositories::wish_lists(client, after, take)
+            })
+            .await
+        }
+        Entity::Ebooks => {
+            export_pages(&path, format, |after, take| {
+                repositories::ebooks(client, after, take)
+            })
+            .await
+        }
+        Entity::Memberships => {
+            export_pages(&path, format, |after, take| {
+                repositories::memberships(client, after, take)
+            })
+            .await
+        }
+        Entity::Snapshots => {
+            export_pages(&path, format, |after, take| {
+                repositories::snapshots(client, after, take)
+            })
+            .await
+        }
+    }
+}
+
+/// dir に entity ごとのファイルと manifest.json を書き出す
+pub async fn export(
+    client: &PrismaClient,
+    dir: &Path,
+    format: Format,
+    entities: &[Entity],
+) -> Result<Manifest> {
+    if entities.is_empty() {
+        return Err(anyhow!("no entities to export"));
+    }
+    fs::create_dir_all(dir)?;
+    let mut counts = BTreeMap::new();
+    for entity in entities {
+        let count = export_entity(client, dir, format, *entity).await?;
+        info!("exported {} {}", count, entity.name());
+        counts.insert(*entity, count);
+    }
+    let manifest = Manifest {
+        format,
+        exported_at: Utc::now().timestamp(),
+        counts,
+    };
+    fs::write(dir.join(MANIFEST), serde_json::to_string_pretty(&manifest)?)?;
+    Ok(manifest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest() {
+        let manifest = Manifest {
+            format: Format::Ndjson,
+            exported_at: 0,
+            counts: BTreeMap::from([(Entity::Snapshots, 3), (Entity::Wishlists, 1)]),
+        };
+        let json = serde_json::to_string(&manifest).unwrap();
+        assert_eq!(
+            json,
+            r#"{"format":"ndjson","exported_at":0,"counts":{"wishlists":1,"snapshots":3}}"#
+        );
+        assert_eq!(serde_json::from_str::<Manifest>(&json).unwrap(), manifest);
+        assert_eq!(Entity::Ebooks.file_name(Format::Parquet), "ebooks.parquet");
+    }
+}
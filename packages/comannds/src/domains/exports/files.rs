@@ -0,0 +1,236 @@
+use anyhow::Result;
+use parquet::file::properties::WriterProperties;
+use parquet::file::reader::SerializedFileReader;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::record::reader::RowIter;
+use parquet::record::RecordWriter;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::marker::PhantomData;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::domains::exports::{Format, Record};
+
+enum Sink {
+    Csv(csv::Writer<File>),
+    Json(BufWriter<File>),
+    Ndjson(BufWriter<File>),
+    Parquet(SerializedFileWriter<File>),
+}
+
+/// 1 entity 分のファイル。ページごとに追記して、最後に finish で閉じる
+pub struct RecordFile<T> {
+    sink: Sink,
+    count: usize,
+    record: PhantomData<T>,
+}
+
+impl<T> RecordFile<T>
+where
+    T: Record,
+    for<'a> &'a [T]: RecordWriter<T>,
+{
+    pub fn create(path: &Path, format: Format) -> Result<RecordFile<T>> {
+        let file = File::create(path)?;
+        let sink = match format {
+            Format::Csv => Sink::Csv(csv::Writer::from_writer(file)),
+            Format::Json => {
+                let mut writer = BufWriter::new(file);
+                writer.write_all(b"[")?;
+                Sink::Json(writer)
+            }
+            Format::Ndjson => Sink::Ndjson(BufWriter::new(file)),
+            Format::Parquet => {
+                let records: &[T] = &[];
+                let properties = Arc::new(WriterProperties::builder().build());
+                Sink::Parquet(SerializedFileWriter::new(
+                    file,
+                    records.schema()?,
+                    properties,
+                )?)
+            }
+        };
+        Ok(RecordFile {
+            sink,
+            count: 0,
+            record: PhantomData,
+        })
+    }
+
+    pub fn write_page(&mut self, records: &[T]) -> Result<()> {
+        match &mut self.sink {
+            Sink::Csv(writer) => {
+                for record in records {
+                    writer.serialize(record)?;
+                }
+            }
+            Sink::Json(writer) => {
+                for (i, record) in records.iter().enumerate() {
+                    if self.count + i > 0 {
+                        writer.write_all(b",")?;
+                    }
+                    writer.write_all(b"\n")?;
+                    serde_json::to_writer(&mut *writer, record)?;
+                }
+            }
+            Sink::Ndjson(writer) => {
+                for record in records {
+                    serde_json::to_writer(&mut *writer, record)?;
+                    writer.write_all(b"\n")?;
+                }
+            }
+            // ページをそのまま 1 つの row group にする
+            Sink::Parquet(writer) => {
+                if !records.is_empty() {
+                    let mut row_group = writer.next_row_group()?;
+                    records.write_to_row_group(&mut row_group)?;
+                    row_group.close()?;
+                }
+            }
+        }
+        self.count += records.len();
+        Ok(())
+    }
+
+    /// 書き込んだ件数を返す
+    pub fn finish(self) -> Result<usize> {
+        match self.sink {
+            Sink::Csv(mut writer) => writer.flush()?,
+            Sink::Json(mut writer) => {
+                writer.write_all(b"\n]\n")?;
+                writer.flush()?;
+            }
+            Sink::Ndjson(mut writer) => writer.flush()?,
+            Sink::Parquet(writer) => {
+                writer.close()?;
+            }
+        }
+        Ok(self.count)
+    }
+}
+
+/// export したファイルを 1 件ずつ読む。json は配列なので一度に読み込む
+pub fn read_records<T: Record + 'static>(
+    path: &Path,
+    format: Format,
+) -> Result<Box<dyn Iterator<Item = Result<T>>>> {
+    let records: Box<dyn Iterator<Item = Result<T>>> = match format {
+        Format::Csv => Box::new(
+            csv::Reader::from_path(path)?
+                .into_deserialize()
+                .map(|record| record.map_err(Into::into)),
+        ),
+        Format::Json => {
+            let file = BufReader::new(File::open(path)?);
+            let records = serde_json::from_reader::<_, Vec<T>>(file)?;
+            Box::new(records.into_iter().map(Ok))
+        }
+        Format::Ndjson => Box::new(
+            BufReader::new(File::open(path)?)
+                .lines()
+                .filter(|line| !matches!(line, Ok(line) if line.trim().is_empty()))
+                .map(|line| Ok(serde_json::from_str(&line?)?)),
+        ),
+        // 行を JSON にしてから他の形式と同じ serde の定義で読む
+        Format::Parquet => {
+            let reader = SerializedFileReader::new(File::open(path)?)?;
+            Box::new(
+                RowIter::from_file_into(Box::new(reader))
+                    .map(|row| Ok(serde_json::from_value(row.to_json_value())?)),
+            )
+        }
+    };
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domains::exports::EbookRecord;
+    use std::fs;
+
+    fn records() -> Vec<EbookRecord> {
+        vec![
+            EbookRecord {
+                id: "B000000001".to_string(),
+                url: "https://www.amazon.co.jp/dp/B000000001".to_string(),
+                title: "title, 1".to_string(),
+                price: 100.0,
+                archived_at: None,
+                watched_at: Some(10),
+            },
+            EbookRecord {
+                id: "B000000002".to_string(),
+                url: "https://www.amazon.co.jp/dp/B000000002".to_string(),
+                title: "title 2".to_string(),
+                price: 200.5,
+                archived_at: Some(20),
+                watched_at: None,
+            },
+        ]
+    }
+
+    fn path(test: &str, format: Format) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!(
+            "{}-{}.{}",
+            test,
+            std::process::id(),
+            format.extension()
+        ))
+    }
+
+    fn write(format: Format) -> String {
+        let path = path("test-write", format);
+        let mut file = RecordFile::create(&path, format).unwrap();
+        let records = records();
+        file.write_page(&records[..1]).unwrap();
+        file.write_page(&records[1..]).unwrap();
+        file.write_page(&[]).unwrap();
+        assert_eq!(file.finish().unwrap(), 2);
+        let written = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        String::from_utf8_lossy(&written).to_string()
+    }
+
+    #[test]
+    fn test_write() {
+        assert_eq!(
+            write(Format::Csv),
+            [
+                "id,url,title,price,archived_at,watched_at",
+                "B000000001,https://www.amazon.co.jp/dp/B000000001,\"title, 1\",100.0,,10",
+                "B000000002,https://www.amazon.co.jp/dp/B000000002,title 2,200.5,20,",
+                "",
+            ]
+            .join("\n")
+        );
+
+        let json = serde_json::from_str::<Vec<EbookRecord>>(&write(Format::Json)).unwrap();
+        assert_eq!(json, records());
+
+        let ndjson = write(Format::Ndjson)
+            .lines()
+            .map(|line| serde_json::from_str::<EbookRecord>(line).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(ndjson, records());
+
+        assert!(write(Format::Parquet).starts_with("PAR1"));
+    }
+
+    #[test]
+    fn test_read() {
+        for format in [Format::Csv, Format::Json, Format::Ndjson, Format::Parquet] {
+            let path = path("test-read", format);
+            let mut file = RecordFile::create(&path, format).unwrap();
+            file.write_page(&records()).unwrap();
+            file.finish().unwrap();
+            let actual = read_records::<EbookRecord>(&path, format)
+                .unwrap()
+                .collect::<Result<Vec<_>>>()
+                .unwrap();
+            fs::remove_file(&path).unwrap();
+            assert_eq!(actual, records(), "{:?}", format);
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use anyhow::Result;
+use db_client::prisma::{ebook, ebook_in_wish_list, ebook_snapshot, wish_list, PrismaClient};
+use prisma_client_rust::Direction::Asc;
+
+use crate::domains::exports::{EbookRecord, MembershipRecord, SnapshotRecord, WishListRecord};
+
+// いずれも id が after より大きいものを id 順に take 件
+
+pub async fn wish_lists(
+    client: &PrismaClient,
+    after: Option<String>,
+    take: i64,
+) -> Result<Vec<WishListRecord>> {
+    let params = after.map(|id| vec![wish_list::id::gt(id)]);
+    let lists = client
+        .wish_list()
+        .find_many(params.unwrap_or_default())
+        .order_by(wish_list::id::order(Asc))
+        .take(take)
+        .exec()
+        .await?;
+    Ok(lists.into_iter().map(WishListRecord::from).collect())
+}
+
+pub async fn ebooks(
+    client: &PrismaClient,
+    after: Option<String>,
+    take: i64,
+) -> Result<Vec<EbookRecord>> {
+    let params = after.map(|id| vec![ebook::id::gt(id)]);
+    let ebooks = client
+        .ebook()
+        .find_many(params.unwrap_or_default())
+        .with(ebook::watched::fetch())
+        .order_by(ebook::id::order(Asc))
+        .take(take)
+        .exec()
+        .await?;
+    Ok(ebooks.into_iter().map(EbookRecord::from).collect())
+}
+
+pub async fn memberships(
+    client: &PrismaClient,
+    after: Option<String>,
+    take: i64,
+) -> Result<Vec<MembershipRecord>> {
+    let params = after.map(|id| vec![ebook_in_wish_list::id::gt(id)]);
+    let memberships = client
+        .ebook_in_wish_list()
+        .find_many(params.unwrap_or_default())
+        .order_by(ebook_in_wish_list::id::order(Asc))
+        .take(take)
+        .exec()
+        .await?;
+    Ok(memberships
+        .into_iter()
+        .map(MembershipRecord::from)
+        .collect())
+}
+
+pub async fn snapshots(
+    client: &PrismaClient,
+    after: Option<String>,
+    take: i64,
+) -> Result<Vec<SnapshotRecord>> {
+    let params = after.map(|id| vec![ebook_snapshot::id::gt(id)]);
+    let snapshots = client
+        .ebook_snapshot()
+        .find_many(params.unwrap_or_default())
+        .order_by(ebook_snapshot::id::order(Asc))
+        .take(take)
+        .exec()
+        .await?;
+    Ok(snapshots.into_iter().map(SnapshotRecord::from).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use db_client::prisma;
+
+    #[tokio::test]
+    async fn test_paging() {
+        dotenv::dotenv().ok();
+
+        let client = prisma::new_client().await.unwrap();
+        let first = snapshots(&client, None, 2).await.unwrap();
+        assert!(first.len() <= 2);
+        let Some(last) = first.last() else {
+            return;
+        };
+        let next = snapshots(&client, Some(last.id.clone()), 2).await.unwrap();
+        assert!(next.iter().all(|s| s.id > last.id));
+    }
+}
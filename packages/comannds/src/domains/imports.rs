@@ -0,0 +1,357 @@
+pub mod repositories;
+
+use anyhow::{anyhow, Result};
+use db_client::prisma::PrismaClient;
+use scraper::asin::Asin;
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::future::Future;
+use std::path::Path;
+use url::Url;
+
+use crate::domains::exports::files::read_records;
+use crate::domains::exports::{
+    EbookRecord, Entity, Manifest, MembershipRecord, Record, SnapshotRecord, WishListRecord,
+    MANIFEST,
+};
+
+/// 一度に書き込む件数。1 ページずつトランザクションになる
+const PAGE_SIZE: usize = 500;
+/// エラーはこの件数までしか表示しない
+const MAX_ERRORS: usize = 20;
+
+#[derive(Debug, Default, PartialEq, Eq, Copy, Clone)]
+pub struct Counts {
+    pub inserted: usize,
+    pub updated: usize,
+    pub skipped: usize,
+}
+
+impl Counts {
+    fn merge(self, other: Counts) -> Counts {
+        Counts {
+            inserted: self.inserted + other.inserted,
+            updated: self.updated + other.updated,
+            skipped: self.skipped + other.skipped,
+        }
+    }
+}
+
+pub type Report = BTreeMap<Entity, Counts>;
+
+/// 書き込む前にアーカイブ全体を読んで、壊れたレコードや参照切れがないか確かめる
+#[derive(Debug, Default)]
+struct Validator {
+    wish_list_ids: HashSet<String>,
+    ebook_ids: HashSet<String>,
+    /// アーカイブにない参照先。DB にあれば問題ない
+    missing_wish_lists: HashSet<String>,
+    missing_ebooks: HashSet<String>,
+    errors: Vec<String>,
+}
+
+impl Validator {
+    fn error(&mut self, entity: Entity, line: usize, message: String) {
+        self.errors
+            .push(format!("{} #{}: {}", entity.name(), line, message));
+    }
+
+    fn require_ebook(&mut self, id: &str) {
+        if !self.ebook_ids.contains(id) {
+            self.missing_ebooks.insert(id.to_string());
+        }
+    }
+
+    fn wish_list(&mut self, line: usize, record: WishListRecord) {
+        if let Err(e) = Url::parse(record.url.as_str()) {
+            self.error(Entity::Wishlists, line, format!("invalid url: {}", e));
+        }
+        self.wish_list_ids.insert(record.id);
+    }
+
+    fn ebook(&mut self, line: usize, record: EbookRecord) {
+        if let Err(e) = Asin::parse(record.id.as_str()) {
+            self.error(Entity::Ebooks, line, e.to_string());
+        }
+        if record.price < 0.0 {
+            self.error(
+                Entity::Ebooks,
+                line,
+                format!("negative price {}", record.price),
+            );
+        }
+        self.ebook_ids.insert(record.id);
+    }
+
+    fn membership(&mut self, _line: usize, record: MembershipRecord) {
+        if !self.wish_list_ids.contains(&record.wish_list_id) {
+            self.missing_wish_lists.insert(record.wish_list_id);
+        }
+        self.require_ebook(record.ebook_id.as_str());
+    }
+
+    fn snapshot(&mut self, line: usize, record: SnapshotRecord) {
+        if record.price < 0.0 || record.points < 0.0 {
+            self.error(
+                Entity::Snapshots,
+                line,
+                "negative price or points".to_string(),
+            );
+        }
+        self.require_ebook(record.ebook_id.as_str());
+    }
+}
+
+fn validate_entity<T, F>(
+    dir: &Path,
+    manifest: &Manifest,
+    entity: Entity,
+    validator: &mut Validator,
+    mut check: F,
+) -> Result<()>
+where
+    T: Record + 'static,
+    F: FnMut(&mut Validator, usize, T),
+{
+    let Some(expected) = manifest.counts.get(&entity) else {
+        return Ok(());
+    };
+    let path = dir.join(entity.file_name(manifest.format));
+    let mut count = 0;
+    for (i, record) in read_records::<T>(&path, manifest.format)?.enumerate() {
+        count += 1;
+        match record {
+            Ok(record) => check(validator, i + 1, record),
+            Err(e) => validator.error(entity, i + 1, e.to_string()),
+        }
+    }
+    if count != *expected {
+        validator.error(
+            entity,
+            0,
+            format!("{} records, but manifest says {}", count, expected),
+        );
+    }
+    Ok(())
+}
+
+fn read_manifest(dir: &Path) -> Result<Manifest> {
+    let manifest = fs::read_to_string(dir.join(MANIFEST))
+        .map_err(|e| anyhow!("{} is not an export archive: {}", dir.display(), e))?;
+    Ok(serde_json::from_str(&manifest)?)
+}
+
+async fn validate(client: &PrismaClient, dir: &Path, manifest: &Manifest) -> Result<()> {
+    let mut validator = Validator::default();
+    // 参照先を先に読むため Entity::ALL の順で確かめる
+    validate_entity(
+        dir,
+        manifest,
+        Entity::Wishlists,
+        &mut validator,
+        Validator::wish_list,
+    )?;
+    validate_entity(
+        dir,
+        manifest,
+        Entity::Ebooks,
+        &mut validator,
+        Validator::ebook,
+    )?;
+    validate_entity(
+        dir,
+        manifest,
+        Entity::Memberships,
+        &mut validator,
+        Validator::membership,
+    )?;
+    validate_entity(
+        dir,
+        manifest,
+        Entity::Snapshots,
+        &mut validator,
+        Validator::snapshot,
+    )?;
+
+    let wish_lists = validator.missing_wish_lists.iter().cloned().collect();
+    for id in repositories::missing_wish_lists(client, wish_lists).await? {
+        validator.errors.push(format!(
+            "wishlist {} is not in the archive nor database",
+            id
+        ));
+    }
+    let ebooks = validator.missing_ebooks.iter().cloned().collect();
+    for id in repositories::missing_ebooks(client, ebooks).await? {
+        validator
+            .errors
+            .push(format!("ebook {} is not in the archive nor database", id));
+    }
+
+    if validator.errors.is_empty() {
+        return Ok(());
+    }
+    let total = validator.errors.len();
+    let mut errors = validator.errors;
+    errors.truncate(MAX_ERRORS);
+    if total > MAX_ERRORS {
+        errors.push(format!("... and {} more", total - MAX_ERRORS));
+    }
+    Err(anyhow!("invalid archive:\n{}", errors.join("\n")))
+}
+
+async fn import_pages<T, F, Fut>(
+    dir: &Path,
+    manifest: &Manifest,
+    entity: Entity,
+    mut write: F,
+) -> Result<Counts>
+where
+    T: Record + 'static,
+    F: FnMut(Vec<T>) -> Fut,
+    Fut: Future<Output = Result<Counts>>,
+{
+    let path = dir.join(entity.file_name(manifest.format));
+    let mut records = read_records::<T>(&path, manifest.format)?.peekable();
+    let mut counts = Counts::default();
+    while records.peek().is_some() {
+        let page = records
+            .by_ref()
+            .take(PAGE_SIZE)
+            .collect::<Result<Vec<_>>>()?;
+        counts = counts.merge(write(page).await?);
+    }
+    Ok(counts)
+}
+
+/// export したディレクトリから復元する。何度流しても同じ結果になる
+pub async fn import(client: &PrismaClient, dir: &Path, dry_run: bool) -> Result<Report> {
+    let manifest = read_manifest(dir)?;
+    validate(client, dir, &manifest).await?;
+
+    let mut report = Report::new();
+    if dry_run {
+        return Ok(report);
+    }
+    for entity in Entity::ALL {
+        if !manifest.counts.contains_key(&entity) {
+            continue;
+        }
+        let counts = match entity {
+            Entity::Wishlists => {
+                import_pages(dir, &manifest, entity, |page| {
+                    repositories::upsert_wish_lists(client, page)
+                })
+                .await?
+            }
+            Entity::Ebooks => {
+                import_pages(dir, &manifest, entity, |page| {
+                    repositories::upsert_ebooks(client, page)
+                })
+                .await?
+            }
+            Entity::Memberships => {
+                import_pages(dir, &manifest, entity, |page| {
+                    repositories::upsert_memberships(client, page)
+                })
+                .await?
+            }
+            Entity::Snapshots => {
+                import_pages(dir, &manifest, entity, |page| {
+                    repositories::insert_snapshots(client, page)
+                })
+                .await?
+            }
+        };
+        info!("imported {} {:?}", entity.name(), counts);
+        report.insert(entity, counts);
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domains::exports::{export, Format};
+    use db_client::prisma;
+
+    #[test]
+    fn test_validator() {
+        let mut validator = Validator::default();
+        validator.wish_list(
+            1,
+            WishListRecord {
+                id: "2BDAPI9RQ09E9".to_string(),
+                url: "https://www.amazon.jp/hz/wishlist/ls/2BDAPI9RQ09E9".to_string(),
+                title: "title".to_string(),
+                scraped_at: 0,
+            },
+        );
+        validator.ebook(
+            1,
+            EbookRecord {
+                id: "B09RQGMYKZ".to_string(),
+                url: "https://www.amazon.co.jp/dp/B09RQGMYKZ".to_string(),
+                title: "title".to_string(),
+                price: 100.0,
+                archived_at: None,
+                watched_at: None,
+            },
+        );
+        validator.ebook(
+            2,
+            EbookRecord {
+                id: "not-an-asin".to_string(),
+                url: "https://www.amazon.co.jp/dp/not-an-asin".to_string(),
+                title: "title".to_string(),
+                price: -1.0,
+                archived_at: None,
+                watched_at: None,
+            },
+        );
+        validator.membership(
+            1,
+            MembershipRecord {
+                id: "1".to_string(),
+                wish_list_id: "3CDAPI9RQ09E9".to_string(),
+                ebook_id: "B09RQGMYKZ".to_string(),
+                added_at: 0,
+                removed_at: None,
+            },
+        );
+
+        assert_eq!(
+            validator.errors,
+            vec![
+                "ebooks #2: not-an-asin is not an ASIN",
+                "ebooks #2: negative price -1",
+            ]
+        );
+        assert_eq!(
+            validator.missing_wish_lists,
+            HashSet::from(["3CDAPI9RQ09E9".to_string()])
+        );
+        assert!(validator.missing_ebooks.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_import_round_trip() {
+        dotenv::dotenv().ok();
+
+        let client = prisma::new_client().await.unwrap();
+        let dir = std::env::temp_dir().join(format!("test-import-{}", std::process::id()));
+        let manifest = export(&client, &dir, Format::Ndjson, &Entity::ALL)
+            .await
+            .unwrap();
+
+        import(&client, &dir, false).await.unwrap();
+        let actual = import(&client, &dir, false).await.unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        // 二回目は何も増えない
+        for (entity, count) in manifest.counts {
+            let counts = actual[&entity];
+            assert_eq!(counts.inserted, 0, "{:?}", entity);
+            assert_eq!(counts.updated + counts.skipped, count, "{:?}", entity);
+        }
+    }
+}
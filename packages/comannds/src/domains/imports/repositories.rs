@@ -0,0 +1,305 @@
+use anyhow::Result;
+use db_client::prisma::{
+    ebook, ebook_in_wish_list, ebook_snapshot, watched_ebook, wish_list, PrismaClient,
+};
+use std::collections::HashSet;
+
+use crate::domains::exports::{
+    EbookRecord, MembershipRecord, Record, SnapshotRecord, WishListRecord,
+};
+use crate::domains::imports::Counts;
+
+/// 同じ id が何度も出てきたら最初のものだけ残す。飛ばした件数も返す
+fn unique_by_id<T: Record>(records: Vec<T>) -> (Vec<T>, usize) {
+    let total = records.len();
+    let mut ids = HashSet::new();
+    let unique = records
+        .into_iter()
+        .filter(|r| ids.insert(r.id().to_string()))
+        .collect::<Vec<_>>();
+    let skipped = total - unique.len();
+    (unique, skipped)
+}
+
+fn upserted(total: usize, existing: usize, skipped: usize) -> Counts {
+    Counts {
+        inserted: total - existing,
+        updated: existing,
+        skipped,
+    }
+}
+
+/// ids のうち DB にないもの
+pub async fn missing_wish_lists(client: &PrismaClient, ids: Vec<String>) -> Result<Vec<String>> {
+    if ids.is_empty() {
+        return Ok(vec![]);
+    }
+    let found = client
+        .wish_list()
+        .find_many(vec![wish_list::id::in_vec(ids.clone())])
+        .exec()
+        .await?
+        .into_iter()
+        .map(|list| list.id)
+        .collect::<HashSet<_>>();
+    Ok(ids.into_iter().filter(|id| !found.contains(id)).collect())
+}
+
+/// ids のうち DB にないもの
+pub async fn missing_ebooks(client: &PrismaClient, ids: Vec<String>) -> Result<Vec<String>> {
+    if ids.is_empty() {
+        return Ok(vec![]);
+    }
+    let found = client
+        .ebook()
+        .find_many(vec![ebook::id::in_vec(ids.clone())])
+        .exec()
+        .await?
+        .into_iter()
+        .map(|ebook| ebook.id)
+        .collect::<HashSet<_>>();
+    Ok(ids.into_iter().filter(|id| !found.contains(id)).collect())
+}
+
+pub async fn upsert_wish_lists(
+    client: &PrismaClient,
+    records: Vec<WishListRecord>,
+) -> Result<Counts> {
+    let (records, skipped) = unique_by_id(records);
+    let ids = records.iter().map(|r| r.id.clone()).collect::<Vec<_>>();
+    // count は where を無視して全件数を返すので find_many で数える
+    let existing = client
+        .wish_list()
+        .find_many(vec![wish_list::id::in_vec(ids)])
+        .exec()
+        .await?
+        .len();
+    let upserts = records
+        .iter()
+        .map(|r| {
+            client.wish_list().upsert(
+                wish_list::id::equals(r.id.clone()),
+                wish_list::create(
+                    r.id.clone(),
+                    r.url.clone(),
+                    r.scraped_at,
+                    r.title.clone(),
+                    vec![],
+                ),
+                vec![
+                    wish_list::url::set(r.url.clone()),
+                    wish_list::scraped_at::set(r.scraped_at),
+                    wish_list::title::set(r.title.clone()),
+                ],
+            )
+        })
+        .collect::<Vec<_>>();
+    client._batch(upserts).await?;
+    Ok(upserted(records.len(), existing, skipped))
+}
+
+pub async fn upsert_ebooks(client: &PrismaClient, records: Vec<EbookRecord>) -> Result<Counts> {
+    let (records, skipped) = unique_by_id(records);
+    let ids = records.iter().map(|r| r.id.clone()).collect::<Vec<_>>();
+    let existing = client
+        .ebook()
+        .find_many(vec![ebook::id::in_vec(ids)])
+        .exec()
+        .await?
+        .len();
+    let upserts = records
+        .iter()
+        .map(|r| {
+            client.ebook().upsert(
+                ebook::id::equals(r.id.clone()),
+                ebook::create(
+                    r.id.clone(),
+                    r.url.clone(),
+                    r.title.clone(),
+                    r.price,
+                    vec![ebook::archived_at::set(r.archived_at)],
+                ),
+                vec![
+                    ebook::url::set(r.url.clone()),
+                    ebook::title::set(r.title.clone()),
+                    ebook::price::set(r.price),
+                    ebook::archived_at::set(r.archived_at),
+                ],
+            )
+        })
+        .collect::<Vec<_>>();
+    let watches = records
+        .iter()
+        .filter_map(|r| r.watched_at.map(|at| (r.id.clone(), at)))
+        .map(|(id, at)| {
+            client.watched_ebook().upsert(
+                watched_ebook::ebook_id::equals(id.clone()),
+                (ebook::id::equals(id), at, vec![]),
+                vec![watched_ebook::watched_at::set(at)],
+            )
+        })
+        .collect::<Vec<_>>();
+    client._batch((upserts, watches)).await?;
+    Ok(upserted(records.len(), existing, skipped))
+}
+
+pub async fn upsert_memberships(
+    client: &PrismaClient,
+    records: Vec<MembershipRecord>,
+) -> Result<Counts> {
+    let (records, skipped) = unique_by_id(records);
+    let ids = records.iter().map(|r| r.id.clone()).collect::<Vec<_>>();
+    let existing = client
+        .ebook_in_wish_list()
+        .find_many(vec![ebook_in_wish_list::id::in_vec(ids)])
+        .exec()
+        .await?
+        .len();
+    let upserts = records
+        .iter()
+        .map(|r| {
+            client.ebook_in_wish_list().upsert(
+                ebook_in_wish_list::id::equals(r.id.clone()),
+                (
+                    wish_list::id::equals(r.wish_list_id.clone()),
+                    ebook::id::equals(r.ebook_id.clone()),
+                    r.added_at,
+                    vec![
+                        ebook_in_wish_list::id::set(r.id.clone()),
+                        ebook_in_wish_list::removed_at::set(r.removed_at),
+                    ],
+                ),
+                vec![
+                    ebook_in_wish_list::added_at::set(r.added_at),
+                    ebook_in_wish_list::removed_at::set(r.removed_at),
+                ],
+            )
+        })
+        .collect::<Vec<_>>();
+    client._batch(upserts).await?;
+    Ok(upserted(records.len(), existing, skipped))
+}
+
+/// 同じ本・同じ取得日時のスナップショットが既にあるもの、同じ id のものは飛ばす
+pub fn new_snapshots(
+    records: Vec<SnapshotRecord>,
+    existing_keys: &HashSet<(String, i64)>,
+    existing_ids: &HashSet<String>,
+) -> Vec<SnapshotRecord> {
+    let mut keys = existing_keys.clone();
+    records
+        .into_iter()
+        .filter(|r| !existing_ids.contains(&r.id))
+        .filter(|r| keys.insert((r.ebook_id.clone(), r.scraped_at)))
+        .collect()
+}
+
+pub async fn insert_snapshots(
+    client: &PrismaClient,
+    records: Vec<SnapshotRecord>,
+) -> Result<Counts> {
+    let total = records.len();
+    let ebook_ids = records.iter().map(|r| r.ebook_id.clone()).collect();
+    let scraped_ats = records.iter().map(|r| r.scraped_at).collect();
+    let ids = records.iter().map(|r| r.id.clone()).collect();
+    let (by_key, by_id) = client
+        ._batch((
+            client.ebook_snapshot().find_many(vec![
+                ebook_snapshot::ebook_id::in_vec(ebook_ids),
+                ebook_snapshot::scraped_at::in_vec(scraped_ats),
+            ]),
+            client
+                .ebook_snapshot()
+                .find_many(vec![ebook_snapshot::id::in_vec(ids)]),
+        ))
+        .await?;
+    let existing_keys = by_key
+        .into_iter()
+        .map(|s| (s.ebook_id, s.scraped_at))
+        .collect();
+    let existing_ids = by_id.into_iter().map(|s| s.id).collect();
+
+    let data = new_snapshots(records, &existing_keys, &existing_ids)
+        .into_iter()
+        .map(|r| {
+            (
+                r.ebook_id,
+                r.scraped_at,
+                r.thumbnail_url,
+                r.price,
+                r.points,
+                r.points_rate,
+                vec![
+                    ebook_snapshot::id::set(r.id),
+                    ebook_snapshot::discount::set(r.discount),
+                    ebook_snapshot::discount_rate::set(r.discount_rate),
+                ],
+            )
+        })
+        .collect::<Vec<_>>();
+    let inserted = data.len();
+    if inserted > 0 {
+        client.ebook_snapshot().create_many(data).exec().await?;
+    }
+    Ok(Counts {
+        inserted,
+        updated: 0,
+        skipped: total - inserted,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(id: &str, ebook_id: &str, scraped_at: i64) -> SnapshotRecord {
+        SnapshotRecord {
+            id: id.to_string(),
+            ebook_id: ebook_id.to_string(),
+            scraped_at,
+            thumbnail_url: String::new(),
+            price: 100.0,
+            discount: None,
+            discount_rate: None,
+            points: 1.0,
+            points_rate: 1.0,
+        }
+    }
+
+    #[test]
+    fn test_unique_by_id() {
+        let records = vec![
+            snapshot("1", "B000000001", 10),
+            snapshot("2", "B000000001", 20),
+            snapshot("1", "B000000002", 30),
+        ];
+        let (unique, skipped) = unique_by_id(records);
+        assert_eq!(
+            unique,
+            vec![
+                snapshot("1", "B000000001", 10),
+                snapshot("2", "B000000001", 20)
+            ]
+        );
+        assert_eq!(skipped, 1);
+    }
+
+    #[test]
+    fn test_new_snapshots() {
+        let records = vec![
+            snapshot("1", "B000000001", 10),
+            snapshot("2", "B000000001", 20),
+            snapshot("3", "B000000002", 10),
+            snapshot("4", "B000000002", 10),
+            snapshot("5", "B000000002", 30),
+        ];
+        let existing_keys = HashSet::from([("B000000001".to_string(), 20)]);
+        let existing_ids = HashSet::from(["5".to_string()]);
+
+        let actual = new_snapshots(records, &existing_keys, &existing_ids)
+            .into_iter()
+            .map(|r| r.id)
+            .collect::<Vec<_>>();
+        assert_eq!(actual, vec!["1", "3"]);
+    }
+}
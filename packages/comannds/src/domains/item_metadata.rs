@@ -1,6 +1,9 @@
+use anyhow::anyhow;
+use scraper::asin::Asin;
+use serde::Serialize;
 use url::Url;
 
-#[derive(Debug, Eq, Ord, PartialEq, PartialOrd, Clone)]
+#[derive(Debug, Eq, Ord, PartialEq, PartialOrd, Clone, Serialize)]
 pub struct ItemMetaData {
     pub id: String,
     pub url: Url,
@@ -18,9 +21,7 @@ impl ItemMetaData {
 
     pub fn new<T: Into<String>>(href: T, title: T, price: T) -> anyhow::Result<ItemMetaData> {
         let url = ItemMetaData::create_url(href.into())?;
-        let path = url.path().to_string();
-        let tmp: Vec<_> = path.split('/').collect();
-        let id = tmp.get(2).unwrap();
+        let id = Asin::from_url(&url).ok_or(anyhow!("no ASIN in {}", url))?;
         let meta = ItemMetaData {
             id: id.to_string(),
             url,
@@ -38,14 +39,14 @@ mod tests {
     #[test]
     fn test_create() {
         let expected = ItemMetaData {
-            id: String::from("2BDAPI9RQ09E9"),
-            url: Url::parse("https://www.amazon.co.jp/dp/2BDAPI9RQ09E9/").unwrap(),
+            id: String::from("B09RQGMYKZ"),
+            url: Url::parse("https://www.amazon.co.jp/dp/B09RQGMYKZ/").unwrap(),
             title: String::from("title"),
             price: String::from("100"),
         };
         assert_eq!(
             ItemMetaData::new(
-                "/dp/2BDAPI9RQ09E9/?coliid=IH".to_string(),
+                "/dp/B09RQGMYKZ/?coliid=IH".to_string(),
                 String::from("title"),
                 String::from("100")
             )
@@ -53,4 +54,16 @@ mod tests {
             expected
         );
     }
+
+    #[test]
+    fn test_create_from_other_url_forms() {
+        for href in [
+            "/%E3%82%BF%E3%82%A4%E3%83%88%E3%83%AB/dp/B09RQGMYKZ/ref=sr_1_1",
+            "/gp/product/B09RQGMYKZ?psc=1",
+        ] {
+            let actual = ItemMetaData::new(href, "title", "100").unwrap();
+            assert_eq!(actual.id, "B09RQGMYKZ");
+        }
+        assert!(ItemMetaData::new("/hz/wishlist/ls/2BDAPI9RQ09E9", "title", "100").is_err());
+    }
 }
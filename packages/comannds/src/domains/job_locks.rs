@@ -0,0 +1,244 @@
+pub mod repositories;
+
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use db_client::prisma::PrismaClient;
+use std::future::Future;
+use std::time::Duration;
+
+const WAIT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// 他のプロセスがロックを持っていたときの振る舞い
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum LockMode {
+    /// エラーにする
+    Fail,
+    /// 空くまで待つ
+    Wait,
+    /// 何もせずに終わる
+    Skip,
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct LockOptions {
+    pub mode: LockMode,
+    /// この秒数更新されないロックは奪ってよい
+    pub ttl: i64,
+}
+
+impl Default for LockOptions {
+    fn default() -> Self {
+        LockOptions {
+            mode: LockMode::Fail,
+            ttl: 60 * 60,
+        }
+    }
+}
+
+impl LockOptions {
+    fn renew_interval(&self) -> Duration {
+        Duration::from_secs((self.ttl / 3).max(1) as u64)
+    }
+}
+
+fn owner() -> String {
+    let host = std::env::var("HOSTNAME").unwrap_or_else(|_| String::from("localhost"));
+    format!(
+        "{}:{}:{}",
+        host,
+        std::process::id(),
+        Utc::now().timestamp_nanos()
+    )
+}
+
+/// job のロックを取る。取れたときはロックの持ち主、Skip で取れなかったときは None
+pub async fn acquire(
+    client: &PrismaClient,
+    job: &str,
+    options: &LockOptions,
+) -> Result<Option<String>> {
+    let owner = owner();
+    loop {
+        let now = Utc::now().timestamp();
+        if repositories::try_acquire(client, job, owner.as_str(), now, now + options.ttl).await? {
+            return Ok(Some(owner));
+        }
+        let holder = repositories::find(client, job)
+            .await?
+            .map(|lock| lock.owner)
+            .unwrap_or_default();
+        match options.mode {
+            LockMode::Fail => return Err(anyhow!("{} is locked by {}", job, holder)),
+            LockMode::Skip => {
+                info!("{} is locked by {}, skipped", job, holder);
+                return Ok(None);
+            }
+            LockMode::Wait => {
+                info!("{} is locked by {}, waiting", job, holder);
+                tokio::time::sleep(WAIT_INTERVAL).await;
+            }
+        }
+    }
+}
+
+/// owner が持つロックを延長しながら run を実行する。
+/// 他に奪われていたら、重ねて動かないよう run を打ち切ってエラーにする
+pub async fn hold<T, F>(
+    client: &PrismaClient,
+    job: &str,
+    owner: &str,
+    options: &LockOptions,
+    run: F,
+) -> Result<T>
+where
+    F: Future<Output = Result<T>>,
+{
+    // 長いジョブでも期限切れで奪われないよう、実行中はロックを延長し続ける
+    let renew = async {
+        loop {
+            tokio::time::sleep(options.renew_interval()).await;
+            let expires_at = Utc::now().timestamp() + options.ttl;
+            match repositories::renew(client, job, owner, expires_at).await {
+                Ok(true) => {}
+                Ok(false) => {
+                    warn!("lock of {} was taken over, aborting", job);
+                    return anyhow!("lost the lock of {}", job);
+                }
+                Err(e) => warn!("can not renew lock of {}: {}", job, e),
+            }
+        }
+    };
+    tokio::select! {
+        result = run => result,
+        lost = renew => Err(lost),
+    }
+}
+
+/// job のロックを取ってから run を実行する。Skip でロックが取れなかったときは None
+pub async fn with_lock<T, F>(
+    client: &PrismaClient,
+    job: &str,
+    options: &LockOptions,
+    run: F,
+) -> Result<Option<T>>
+where
+    F: Future<Output = Result<T>>,
+{
+    let Some(owner) = acquire(client, job, options).await? else {
+        return Ok(None);
+    };
+    let result = hold(client, job, owner.as_str(), options, run).await;
+    repositories::release(client, job, owner.as_str()).await?;
+    result.map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domains::job_runs::{self, Status, Summary};
+    use db_client::prisma;
+
+    #[test]
+    fn test_renew_interval() {
+        let options = LockOptions {
+            mode: LockMode::Wait,
+            ttl: 90,
+        };
+        assert_eq!(options.renew_interval(), Duration::from_secs(30));
+        let options = LockOptions {
+            mode: LockMode::Wait,
+            ttl: 1,
+        };
+        assert_eq!(options.renew_interval(), Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn test_with_lock() {
+        dotenv::dotenv().ok();
+
+        let client = prisma::new_client().await.unwrap();
+        let job = "unit-test-with-lock";
+        let skip = LockOptions {
+            mode: LockMode::Skip,
+            ..LockOptions::default()
+        };
+        let fail = LockOptions::default();
+
+        let actual = with_lock(&client, job, &skip, async {
+            // 実行中は他からロックを取れない
+            let inner = with_lock(&client, job, &skip, async { anyhow::Ok(1) }).await?;
+            assert_eq!(inner, None);
+            assert!(with_lock(&client, job, &fail, async { anyhow::Ok(1) })
+                .await
+                .is_err());
+            anyhow::Ok(2)
+        })
+        .await
+        .unwrap();
+        assert_eq!(actual, Some(2));
+
+        // 終わったら解放されている
+        let actual = with_lock(&client, job, &fail, async { anyhow::Ok(3) })
+            .await
+            .unwrap();
+        assert_eq!(actual, Some(3));
+    }
+
+    #[tokio::test]
+    async fn test_with_lock_taken_over() {
+        dotenv::dotenv().ok();
+
+        let client = prisma::new_client().await.unwrap();
+        let job = "unit-test-with-lock-taken-over";
+        repositories::release(&client, job, "other").await.unwrap();
+        let options = LockOptions {
+            mode: LockMode::Fail,
+            ttl: 1,
+        };
+
+        let actual = with_lock(&client, job, &options, async {
+            // 期限が切れたものとして他のプロセスに奪われる
+            let later = Utc::now().timestamp() + 60;
+            assert!(repositories::try_acquire(&client, job, "other", later, later + 60).await?);
+            tokio::time::sleep(Duration::from_secs(10)).await;
+            anyhow::Ok(1)
+        })
+        .await;
+        assert!(actual.is_err());
+
+        // 奪った側のロックは残っている
+        let lock = repositories::find(&client, job).await.unwrap().unwrap();
+        assert_eq!(lock.owner, "other");
+        repositories::release(&client, job, "other").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_hold_taken_over_is_recorded() {
+        dotenv::dotenv().ok();
+
+        let client = prisma::new_client().await.unwrap();
+        let job = "unit-test-hold-taken-over";
+        repositories::release(&client, job, "other").await.unwrap();
+        let options = LockOptions {
+            mode: LockMode::Fail,
+            ttl: 1,
+        };
+
+        let owner = acquire(&client, job, &options).await.unwrap().unwrap();
+        let run = async {
+            let later = Utc::now().timestamp() + 60;
+            assert!(repositories::try_acquire(&client, job, "other", later, later + 60).await?);
+            tokio::time::sleep(Duration::from_secs(10)).await;
+            Ok(Summary::default())
+        };
+        let held = hold(&client, job, owner.as_str(), &options, run);
+        assert!(job_runs::record(&client, job, held).await.is_err());
+
+        // 打ち切られた実行は running のまま残らない
+        let runs = job_runs::repositories::select_recent(&client, Some(job.to_string()), 1)
+            .await
+            .unwrap();
+        assert_eq!(runs[0].status, Status::Failed.as_str());
+        repositories::release(&client, job, "other").await.unwrap();
+    }
+}
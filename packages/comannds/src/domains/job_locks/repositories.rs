@@ -0,0 +1,108 @@
+use anyhow::Result;
+use db_client::prisma::job_lock::Data as JobLockData;
+use db_client::prisma::{job_lock, PrismaClient};
+use prisma_client_rust::prisma_errors::query_engine::UniqueKeyViolation;
+
+pub async fn try_acquire(
+    client: &PrismaClient,
+    job: &str,
+    owner: &str,
+    now: i64,
+    expires_at: i64,
+) -> Result<bool> {
+    let created = client
+        .job_lock()
+        .create(job.to_string(), owner.to_string(), now, expires_at, vec![])
+        .exec()
+        .await;
+    match created {
+        Ok(_) => return Ok(true),
+        // 既に誰かが持っている。それ以外の失敗はそのまま返す
+        Err(e) if e.is_prisma_error::<UniqueKeyViolation>() => {}
+        Err(e) => return Err(e.into()),
+    }
+
+    // 期限が切れたロックは、落ちたプロセスのものとみなして奪う
+    let taken = client
+        .job_lock()
+        .update_many(
+            vec![
+                job_lock::job::equals(job.to_string()),
+                job_lock::expires_at::lt(now),
+            ],
+            vec![
+                job_lock::owner::set(owner.to_string()),
+                job_lock::acquired_at::set(now),
+                job_lock::expires_at::set(expires_at),
+            ],
+        )
+        .exec()
+        .await?;
+    Ok(taken > 0)
+}
+
+pub async fn renew(client: &PrismaClient, job: &str, owner: &str, expires_at: i64) -> Result<bool> {
+    let renewed = client
+        .job_lock()
+        .update_many(
+            vec![
+                job_lock::job::equals(job.to_string()),
+                job_lock::owner::equals(owner.to_string()),
+            ],
+            vec![job_lock::expires_at::set(expires_at)],
+        )
+        .exec()
+        .await?;
+    Ok(renewed > 0)
+}
+
+pub async fn release(client: &PrismaClient, job: &str, owner: &str) -> Result<()> {
+    client
+        .job_lock()
+        .delete_many(vec![
+            job_lock::job::equals(job.to_string()),
+            job_lock::owner::equals(owner.to_string()),
+        ])
+        .exec()
+        .await?;
+    Ok(())
+}
+
+pub async fn find(client: &PrismaClient, job: &str) -> Result<Option<JobLockData>> {
+    let lock = client
+        .job_lock()
+        .find_unique(job_lock::job::equals(job.to_string()))
+        .exec()
+        .await?;
+    Ok(lock)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use db_client::prisma;
+
+    #[tokio::test]
+    async fn test_lock() {
+        dotenv::dotenv().ok();
+
+        let client = prisma::new_client().await.unwrap();
+        let job = "unit-test-lock";
+        release(&client, job, "a").await.unwrap();
+        release(&client, job, "b").await.unwrap();
+
+        assert!(try_acquire(&client, job, "a", 100, 200).await.unwrap());
+        assert!(!try_acquire(&client, job, "b", 150, 250).await.unwrap());
+        assert!(renew(&client, job, "a", 300).await.unwrap());
+        assert!(!renew(&client, job, "b", 300).await.unwrap());
+
+        // 期限切れなら奪える
+        assert!(try_acquire(&client, job, "b", 301, 400).await.unwrap());
+        assert_eq!(find(&client, job).await.unwrap().unwrap().owner, "b");
+
+        release(&client, job, "a").await.unwrap();
+        assert!(find(&client, job).await.unwrap().is_some());
+        release(&client, job, "b").await.unwrap();
+        assert!(find(&client, job).await.unwrap().is_none());
+    }
+}
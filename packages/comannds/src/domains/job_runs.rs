@@ -0,0 +1,157 @@
+pub mod repositories;
+
+use anyhow::Result;
+use chrono::Utc;
+use db_client::prisma::job_run;
+use db_client::prisma::PrismaClient;
+use std::future::Future;
+
+const MAX_ERRORS: usize = 5;
+
+/// ジョブで処理した件数と失敗の内容
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct Summary {
+    pub succeeded: i32,
+    pub failed: i32,
+    pub errors: Vec<String>,
+}
+
+impl Summary {
+    pub fn from_results<T>(results: Vec<Result<T>>) -> Summary {
+        results
+            .into_iter()
+            .fold(Summary::default(), |mut summary, result| {
+                match result {
+                    Ok(_) => summary.succeeded += 1,
+                    Err(e) => {
+                        summary.failed += 1;
+                        summary.errors.push(e.to_string());
+                    }
+                }
+                summary
+            })
+    }
+
+    pub fn merge(mut self, other: Summary) -> Summary {
+        self.succeeded += other.succeeded;
+        self.failed += other.failed;
+        self.errors.extend(other.errors);
+        self
+    }
+
+    pub fn status(&self) -> Status {
+        match (self.succeeded, self.failed) {
+            (_, 0) => Status::Succeeded,
+            (0, _) => Status::Failed,
+            _ => Status::Partial,
+        }
+    }
+
+    /// 全部残すと長くなるので先頭の数件だけ
+    pub fn error_summary(&self) -> Option<String> {
+        if self.errors.is_empty() {
+            return None;
+        }
+        let mut lines = self
+            .errors
+            .iter()
+            .take(MAX_ERRORS)
+            .cloned()
+            .collect::<Vec<_>>();
+        if self.errors.len() > MAX_ERRORS {
+            lines.push(format!("... and {} more", self.errors.len() - MAX_ERRORS));
+        }
+        Some(lines.join("\n"))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Status {
+    Running,
+    Succeeded,
+    Partial,
+    Failed,
+}
+
+impl Status {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Status::Running => "running",
+            Status::Succeeded => "succeeded",
+            Status::Partial => "partial",
+            Status::Failed => "failed",
+        }
+    }
+}
+
+/// ジョブの開始と終了を JobRun に記録する
+pub async fn record<F>(client: &PrismaClient, job: &str, run: F) -> Result<Summary>
+where
+    F: Future<Output = Result<Summary>>,
+{
+    let started = repositories::start(client, job, Utc::now().timestamp()).await?;
+    let result = run.await;
+    let finished_at = Utc::now().timestamp();
+    let finished = match &result {
+        Ok(summary) => {
+            repositories::finish(
+                client,
+                started.id.clone(),
+                summary.status().as_str(),
+                finished_at,
+                vec![
+                    job_run::succeeded::set(summary.succeeded),
+                    job_run::failed::set(summary.failed),
+                    job_run::error::set(summary.error_summary()),
+                ],
+            )
+            .await
+        }
+        Err(e) => {
+            repositories::finish(
+                client,
+                started.id.clone(),
+                Status::Failed.as_str(),
+                finished_at,
+                vec![job_run::error::set(Some(e.to_string()))],
+            )
+            .await
+        }
+    };
+    // 記録に失敗してもジョブ自体の結果を優先する
+    if let Err(e) = finished {
+        error!("can not record the end of {} ({}): {}", job, started.id, e);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+
+    #[test]
+    fn test_summary() {
+        let results: Vec<Result<()>> = vec![Ok(()), Err(anyhow!("captcha")), Ok(())];
+        let actual = Summary::from_results(results);
+        assert_eq!(actual.succeeded, 2);
+        assert_eq!(actual.failed, 1);
+        assert_eq!(actual.status(), Status::Partial);
+        assert_eq!(actual.error_summary(), Some(String::from("captcha")));
+
+        assert_eq!(Summary::default().status(), Status::Succeeded);
+        let failed = Summary::from_results::<()>(vec![Err(anyhow!("a"))]);
+        assert_eq!(failed.status(), Status::Failed);
+        assert_eq!(actual.merge(failed).failed, 2);
+    }
+
+    #[test]
+    fn test_error_summary() {
+        let results = (0..7)
+            .map(|i| Err(anyhow!("error {}", i)))
+            .collect::<Vec<Result<()>>>();
+        let actual = Summary::from_results(results).error_summary().unwrap();
+        assert_eq!(actual.lines().count(), MAX_ERRORS + 1);
+        assert!(actual.ends_with("... and 2 more"));
+    }
+}
@@ -0,0 +1,96 @@
+use crate::domains::job_runs::Status;
+use anyhow::Result;
+use db_client::prisma::job_run::Data as JobRunData;
+use db_client::prisma::{job_run, PrismaClient};
+use prisma_client_rust::Direction::Desc;
+
+pub async fn start(client: &PrismaClient, job: &str, started_at: i64) -> Result<JobRunData> {
+    let run = client
+        .job_run()
+        .create(
+            job.to_string(),
+            Status::Running.as_str().to_string(),
+            started_at,
+            vec![],
+        )
+        .exec()
+        .await?;
+    Ok(run)
+}
+
+pub async fn finish(
+    client: &PrismaClient,
+    id: String,
+    status: &str,
+    finished_at: i64,
+    params: Vec<job_run::SetParam>,
+) -> Result<JobRunData> {
+    let mut params = params;
+    params.push(job_run::status::set(status.to_string()));
+    params.push(job_run::finished_at::set(Some(finished_at)));
+    let run = client
+        .job_run()
+        .update(job_run::id::equals(id), params)
+        .exec()
+        .await?;
+    Ok(run)
+}
+
+pub async fn select_recent(
+    client: &PrismaClient,
+    job: Option<String>,
+    limit: i64,
+) -> Result<Vec<JobRunData>> {
+    let filter = job.map(job_run::job::equals).into_iter().collect();
+    let runs = client
+        .job_run()
+        .find_many(filter)
+        .order_by(job_run::started_at::order(Desc))
+        .take(limit)
+        .exec()
+        .await?;
+    Ok(runs)
+}
+
+pub async fn find(client: &PrismaClient, id: String) -> Result<Option<JobRunData>> {
+    let run = client
+        .job_run()
+        .find_unique(job_run::id::equals(id))
+        .exec()
+        .await?;
+    Ok(run)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use db_client::prisma;
+
+    #[tokio::test]
+    async fn test_start_and_finish() {
+        dotenv::dotenv().ok();
+
+        let client = prisma::new_client().await.unwrap();
+        let run = start(&client, "unit-test", 0).await.unwrap();
+        assert_eq!(run.status, "running");
+
+        let finished = finish(
+            &client,
+            run.id.clone(),
+            "succeeded",
+            1,
+            vec![job_run::succeeded::set(2)],
+        )
+        .await
+        .unwrap();
+        assert_eq!(finished.succeeded, 2);
+        assert_eq!(finished.finished_at, Some(1));
+
+        let found = find(&client, run.id).await.unwrap().unwrap();
+        assert_eq!(found.status, "succeeded");
+        let recent = select_recent(&client, Some(String::from("unit-test")), 1)
+            .await
+            .unwrap();
+        assert_eq!(recent.len(), 1);
+    }
+}
@@ -1,39 +1,44 @@
+pub mod destinations;
+mod discord;
+mod repositories;
+pub mod schedule;
+pub mod templates;
+pub mod thresholds;
+
+use crate::config::{redact, Config, DiscordConfig};
+use crate::context::{Context, Notifier};
+use crate::domains::rules::evaluator::Facts;
+use crate::domains::rules::Rule;
+use crate::locale::Locale;
 use anyhow::{anyhow, Result};
-use chrono::{FixedOffset, TimeZone};
+use chrono::{TimeZone, Utc};
 use db_client::prisma::ebook::Data as EBookData;
 use db_client::prisma::ebook_snapshot::Data as EBookSnapShotData;
+use db_client::prisma::queued_notification::Data as QueuedNotificationData;
 use db_client::prisma::wish_list::Data as WishListData;
-use envy;
-use serde::Deserialize;
-use webhook::client::WebhookClient;
+use destinations::Destination;
+pub use discord::Discord;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use templates::{ContentContext, ItemContext, Templates};
+use thresholds::{Thresholds, Tier};
 use webhook::models::Message;
 
-#[derive(Deserialize, Debug)]
-struct Config {
-    bot_name: String,
-    avatar_url: String,
-    alert_chanel: String,
-    sale_chanel: String,
-}
+pub async fn send_alert_message<T: AsRef<str>>(ctx: &Context<'_>, text: T) -> Result<bool> {
+    let discord = &ctx.config.discord;
 
-pub async fn send_alert_message<T: AsRef<str>>(text: T) -> Result<bool> {
-    let config = envy::prefixed("DISCORD_").from_env::<Config>()?;
+    let mut message = Message::new();
+    message.username(discord.bot_name.as_ref());
+    message.avatar_url(discord.avatar_url.as_ref());
+    message.content(discord::truncate(text, discord::MAX_CONTENT).as_str());
+    ctx.notifier
+        .send(discord.alert_chanel.as_ref(), &message)
+        .await?;
 
-    let client: WebhookClient = WebhookClient::new(config.alert_chanel.as_ref());
-    let result = client
-        .send(|message| {
-            message.username(config.bot_name.as_ref());
-            message.avatar_url(config.avatar_url.as_ref());
-            message.content(text.as_ref());
-            message
-        })
-        .await
-        .unwrap();
-
-    Ok(result)
+    Ok(true)
 }
 
-#[derive(Debug, PartialEq, Copy, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Copy, Clone)]
 enum EmbedColor {
     Red = 15548997,
     Green = 5763719,
@@ -47,131 +52,394 @@ impl From<EmbedColor> for String {
     }
 }
 
-fn get_color(snapshot: &EBookSnapShotData) -> EmbedColor {
-    let discount_rate = snapshot.discount_rate.unwrap_or(0.0);
-    let points_rate = snapshot.points_rate;
-
-    if !(discount_rate >= 20.0 || points_rate >= 20.0) {
-        return EmbedColor::Grey;
+impl From<Tier> for EmbedColor {
+    fn from(tier: Tier) -> Self {
+        match tier {
+            Tier::None => EmbedColor::Grey,
+            Tier::Low => EmbedColor::Green,
+            Tier::Middle => EmbedColor::Yellow,
+            Tier::High => EmbedColor::Red,
+        }
     }
-
-    if discount_rate >= 35.0 || points_rate >= 35.0 {
-        return EmbedColor::Red;
-    }
-
-    if discount_rate >= 30.0 || points_rate >= 30.0 {
-        return EmbedColor::Yellow;
-    }
-
-    EmbedColor::Green
 }
 
-#[derive(Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 struct EmbedItem {
     title: String,
     url: String,
     color: EmbedColor,
-    price: String,
-    discount_rate: String,
-    points_rate: String,
-    update_datetime: String,
+    fields: Vec<(String, String)>,
 }
 
 #[derive(Debug)]
 struct SaleNotification {
-    to: String,
+    to: Vec<String>,
     from_user_name: String,
     from_avatar_url: String,
     content: String,
     embeds: Vec<EmbedItem>,
 }
 
+fn ebooks(data: &WishListData) -> Result<Vec<EBookData>> {
+    let ebook_in_wish_list = data
+        .ebook_in_wish_list
+        .clone()
+        .ok_or(anyhow!("ebooks of {} are not fetched", data.title))?;
+    let ebooks = ebook_in_wish_list
+        .iter()
+        .filter_map(|x| x.ebook.clone())
+        .map(|ebook| *ebook)
+        .collect::<Vec<_>>();
+    Ok(ebooks)
+}
+
+fn latest_snapshot(ebook: &EBookData) -> Option<&EBookSnapShotData> {
+    ebook.snapshots.as_ref()?.first()
+}
+
 impl SaleNotification {
-    fn new(data: &WishListData) -> Option<SaleNotification> {
-        let config = envy::prefixed("DISCORD_").from_env::<Config>().ok()?;
-        let ebook_in_wish_list = data.ebook_in_wish_list.clone()?;
-        let embeds = ebook_in_wish_list
+    fn build(
+        config: &Config,
+        data: &WishListData,
+        rule: Option<&Rule>,
+        filter: impl Fn(&EmbedItem, &Facts) -> bool,
+    ) -> Result<SaleNotification> {
+        let thresholds = config
+            .thresholds
+            .overlay(data.sale_threshold.clone().flatten().as_deref())?;
+        let templates = &config.templates;
+        let locale = config.locale;
+
+        let embeds = ebooks(data)?
             .iter()
-            .filter_map(|x| x.ebook.clone())
-            .filter_map(|ebook| EmbedItem::new(&ebook))
+            .filter_map(|ebook| {
+                let snapshot = latest_snapshot(ebook)?;
+                let facts = Facts::new(data, ebook, snapshot);
+                let item = EmbedItem::new(ebook, snapshot, &facts, &thresholds, templates, locale)?;
+                filter(&item, &facts).then_some(item)
+            })
             .collect::<Vec<_>>();
-        Some(SaleNotification {
-            to: config.sale_chanel,
-            from_user_name: config.bot_name,
-            from_avatar_url: config.avatar_url,
-            content: format!("{} のセール情報", data.title),
+        let content = templates.render_content(&ContentContext {
+            wish_list: data.title.clone(),
+            rule: rule.map(|rule| rule.name.clone()),
+        })?;
+        let destinations = match rule.and_then(|rule| rule.channel.as_ref()) {
+            Some(channel) => vec![Destination::parse(channel)],
+            None => destinations::of_wish_list(data),
+        };
+
+        Ok(SaleNotification {
+            to: destinations::route(
+                &destinations,
+                &config.notifiers,
+                config.discord.sale_chanel.as_str(),
+            ),
+            from_user_name: config.discord.bot_name.clone(),
+            from_avatar_url: config.discord.avatar_url.clone(),
+            content: discord::truncate(content, discord::MAX_CONTENT),
             embeds,
         })
     }
+
+    fn new(config: &Config, data: &WishListData) -> Result<SaleNotification> {
+        SaleNotification::build(config, data, None, |item, _| item.color != EmbedColor::Grey)
+    }
+
+    fn for_rule(config: &Config, data: &WishListData, rule: &Rule) -> Result<SaleNotification> {
+        SaleNotification::build(config, data, Some(rule), |_, facts| rule.matches(facts))
+    }
 }
 
 impl EmbedItem {
-    fn new(ebook: &EBookData) -> Option<EmbedItem> {
-        let snapshots = ebook.snapshots.clone()?;
-        let latest_snapshot = snapshots.first()?;
-        let offset = FixedOffset::east_opt(9 * 3600).unwrap();
-        let date = offset.timestamp_opt(latest_snapshot.scraped_at, 0).unwrap();
+    fn new(
+        ebook: &EBookData,
+        snapshot: &EBookSnapShotData,
+        facts: &Facts,
+        thresholds: &Thresholds,
+        templates: &Templates,
+        locale: Locale,
+    ) -> Option<EmbedItem> {
+        let date = schedule::jst()
+            .timestamp_opt(snapshot.scraped_at, 0)
+            .unwrap();
+        let context = ItemContext::new(facts.clone(), ebook.url.clone(), locale.datetime(&date));
+        let fields = match templates.render_fields(&context) {
+            Ok(fields) => fields,
+            Err(e) => {
+                warn!("{}", e);
+                return None;
+            }
+        };
 
         Some(EmbedItem {
-            title: ebook.title.clone(),
+            title: discord::truncate(&ebook.title, discord::MAX_TITLE),
             url: ebook.url.clone(),
-            color: get_color(latest_snapshot),
-            price: format!("¥{}", latest_snapshot.price),
-            discount_rate: format!("{:.2}%", latest_snapshot.discount_rate.unwrap_or(0.0)),
-            points_rate: format!("{:.2}%", latest_snapshot.points_rate),
-            update_datetime: date.format("%Y/%m/%d %H:%M:%S %Z").to_string(),
+            color: thresholds.tier(snapshot).into(),
+            fields,
         })
     }
-}
 
-fn convert_from(data: &WishListData) -> Option<Vec<Message>> {
-    let notification = SaleNotification::new(data)?;
-
-    let chunks = notification.embeds.chunks(10).collect::<Vec<_>>();
+    fn size(&self) -> usize {
+        let fields = self
+            .fields
+            .iter()
+            .map(|(name, value)| {
+                name.chars().count().min(discord::MAX_FIELD_NAME)
+                    + value.chars().count().min(discord::MAX_FIELD_VALUE)
+            })
+            .sum::<usize>();
+        self.title.chars().count() + fields
+    }
+}
 
-    let result = chunks
+fn convert_from(notification: &SaleNotification) -> Vec<Message> {
+    discord::chunk(&notification.embeds, |item| item.size())
         .iter()
-        .filter_map(|items| {
+        .map(|items| {
             let mut message = Message::new();
             message.username(notification.from_user_name.as_str());
             message.avatar_url(notification.from_avatar_url.as_str());
             message.content(notification.content.as_str());
-            items
-                .iter()
-                .filter(|item| item.color != EmbedColor::Grey)
-                .for_each(|item| {
-                    let color: String = item.color.into();
-                    message.embed(move |embed| {
-                        embed
-                            .title(item.title.as_str())
-                            .url(item.url.as_ref())
-                            .color(color.as_str())
-                            .field("金額", item.price.as_str(), true)
-                            .field("値引き率", item.discount_rate.as_str(), true)
-                            .field("ポイント還元率", item.points_rate.as_str(), true)
-                            .field("更新日", item.update_datetime.as_str(), true)
-                    });
+            items.iter().for_each(|item| {
+                let color: String = item.color.into();
+                message.embed(move |embed| {
+                    let embed = embed
+                        .title(item.title.as_str())
+                        .url(item.url.as_ref())
+                        .color(color.as_str());
+                    item.fields.iter().fold(embed, |embed, (name, value)| {
+                        embed.field(
+                            discord::truncate(name, discord::MAX_FIELD_NAME).as_str(),
+                            discord::truncate(value, discord::MAX_FIELD_VALUE).as_str(),
+                            true,
+                        )
+                    })
                 });
-            if message.embeds.is_empty() {
-                return None;
-            }
-            Some(message)
+            });
+            message
         })
-        .collect::<Vec<_>>();
+        .collect::<Vec<_>>()
+}
 
-    Some(result)
+/// 送信せずに、送られるはずのメッセージを返す
+#[derive(Serialize, Debug)]
+pub struct Preview {
+    pub to: Vec<String>,
+    pub messages: Vec<Message>,
+    #[serde(skip)]
+    content: String,
+    #[serde(skip)]
+    embeds: Vec<EmbedItem>,
+}
+
+impl From<SaleNotification> for Preview {
+    fn from(notification: SaleNotification) -> Self {
+        Preview {
+            messages: convert_from(&notification),
+            to: notification.to,
+            content: notification.content,
+            embeds: notification.embeds,
+        }
+    }
 }
 
-pub async fn notify(data: &WishListData) -> Result<bool> {
-    let config = envy::prefixed("DISCORD_").from_env::<Config>()?;
+impl Preview {
+    pub fn table(&self) -> String {
+        let mut lines = vec![
+            format!("to: {}", self.to.join(", ")),
+            format!("content: {}", self.content),
+            format!("messages: {}", self.messages.len()),
+        ];
+        for item in &self.embeds {
+            lines.push(format!("  [{:?}] {} {}", item.color, item.title, item.url));
+            for (name, value) in &item.fields {
+                lines.push(format!("      {}: {}", name, value));
+            }
+        }
+        lines.join("\n")
+    }
+}
 
-    let messages = convert_from(data).ok_or(anyhow!("can not create message"))?;
-    if messages.is_empty() {
-        info!("no messages for {}", data.title)
+pub fn render(config: &Config, data: &WishListData, rules: &[Rule]) -> Result<Vec<Preview>> {
+    let mut notifications = vec![SaleNotification::new(config, data)?];
+    for rule in rules
+        .iter()
+        .filter(|rule| rule.applies_to(&data.id, data.user_id.as_deref()))
+    {
+        notifications.push(SaleNotification::for_rule(config, data, rule)?);
     }
-    let client: WebhookClient = WebhookClient::new(config.sale_chanel.as_ref());
+    Ok(notifications
+        .into_iter()
+        .filter(|notification| !notification.embeds.is_empty())
+        .map(Preview::from)
+        .collect())
+}
+
+async fn send_to(notifier: &dyn Notifier, to: &str, messages: &[Message]) -> Result<()> {
     for message in messages {
-        client.send_message(&message).await.unwrap();
+        notifier.send(to, message).await?;
+    }
+    Ok(())
+}
+
+/// 一つの宛先に失敗しても残りの宛先には送る
+async fn send(notifier: &dyn Notifier, notification: &SaleNotification) -> Result<()> {
+    let messages = convert_from(notification);
+    let mut errors = vec![];
+    for to in &notification.to {
+        if let Err(e) = send_to(notifier, to.as_str(), &messages).await {
+            warn!("failed to send to {}: {}", redact(to), e);
+            errors.push(format!("{}: {}", redact(to), e));
+        }
+    }
+    if errors.is_empty() {
+        return Ok(());
+    }
+    Err(anyhow!(
+        "failed to send to {} of {} destinations:\n{}",
+        errors.len(),
+        notification.to.len(),
+        errors.join("\n")
+    ))
+}
+
+// 通知を控える時間帯やダイジェスト配信の場合は送らずに貯めておく
+async fn dispatch(ctx: &Context<'_>, notification: &SaleNotification) -> Result<()> {
+    let config = ctx.config;
+    let now = Utc::now();
+    if notification.embeds.is_empty() || !config.schedule.should_queue(now) {
+        return send(ctx.notifier, notification).await;
+    }
+
+    let embeds = notification
+        .embeds
+        .iter()
+        .map(serde_json::to_string)
+        .collect::<Result<Vec<_>, _>>()?;
+    for to in &notification.to {
+        repositories::enqueue(
+            ctx.client().await?,
+            to.as_str(),
+            notification.content.as_str(),
+            &embeds,
+            now.timestamp(),
+        )
+        .await?;
+    }
+    info!(
+        "{}",
+        config
+            .locale
+            .queued(notification.embeds.len(), notification.content.as_str())
+    );
+    Ok(())
+}
+
+pub async fn notify(ctx: &Context<'_>, data: &WishListData) -> Result<bool> {
+    let config = ctx.config;
+    let notification = SaleNotification::new(config, data)?;
+    if notification.embeds.is_empty() {
+        info!("{}", config.locale.no_messages(data.title.as_str()))
+    }
+    dispatch(ctx, &notification).await?;
+
+    Ok(true)
+}
+
+pub async fn notify_rules(ctx: &Context<'_>, data: &WishListData, rules: &[Rule]) -> Result<bool> {
+    let config = ctx.config;
+    for rule in rules
+        .iter()
+        .filter(|rule| rule.applies_to(&data.id, data.user_id.as_deref()))
+    {
+        let notification = SaleNotification::for_rule(config, data, rule)?;
+        if notification.embeds.is_empty() {
+            info!(
+                "{}",
+                config
+                    .locale
+                    .no_matches(data.title.as_str(), rule.name.as_str())
+            )
+        }
+        dispatch(ctx, &notification).await?;
+    }
+
+    Ok(true)
+}
+
+fn digest_from(
+    config: &DiscordConfig,
+    queued: &[QueuedNotificationData],
+    locale: Locale,
+) -> Vec<SaleNotification> {
+    let mut groups: BTreeMap<(String, String), Vec<EmbedItem>> = BTreeMap::new();
+    for item in queued {
+        let embed = match serde_json::from_str::<EmbedItem>(item.embed.as_str()) {
+            Ok(embed) => embed,
+            Err(e) => {
+                warn!("broken queued notification id:{} error:{}", item.id, e);
+                continue;
+            }
+        };
+        let group = groups
+            .entry((item.destination.clone(), item.content.clone()))
+            .or_default();
+        // 同じ本は最新の情報だけ残す
+        group.retain(|x| x.url != embed.url);
+        group.push(embed);
+    }
+    groups
+        .into_iter()
+        .map(|((to, content), embeds)| SaleNotification {
+            to: vec![to],
+            from_user_name: config.bot_name.clone(),
+            from_avatar_url: config.avatar_url.clone(),
+            content: discord::truncate(locale.digest(content.as_str()), discord::MAX_CONTENT),
+            embeds,
+        })
+        .collect()
+}
+
+async fn send_digest(ctx: &Context<'_>, queued: &[QueuedNotificationData]) -> Result<()> {
+    let config = ctx.config;
+    for notification in digest_from(&config.discord, queued, config.locale) {
+        send(ctx.notifier, &notification).await?;
+    }
+    Ok(())
+}
+
+pub async fn deliver_digest(ctx: &Context<'_>) -> Result<bool> {
+    let config = ctx.config;
+    let client = ctx.client().await?;
+    let queued = repositories::select_queued(client).await?;
+    let Some(oldest) = queued.first() else {
+        return Ok(false);
+    };
+    let now = Utc::now();
+    if !config.schedule.is_due(now, oldest.queued_at) {
+        info!("{}", config.locale.waiting(queued.len()));
+        return Ok(false);
+    }
+
+    // 送れた宛先の分はすぐに配信済みにして、後で失敗しても二重に送らない
+    let mut by_destination: BTreeMap<String, Vec<QueuedNotificationData>> = BTreeMap::new();
+    for item in queued {
+        by_destination
+            .entry(item.destination.clone())
+            .or_default()
+            .push(item);
+    }
+    let mut errors = vec![];
+    for items in by_destination.into_values() {
+        if let Err(e) = send_digest(ctx, &items).await {
+            errors.push(e.to_string());
+            continue;
+        }
+        let ids = items.into_iter().map(|x| x.id).collect::<Vec<_>>();
+        repositories::mark_delivered(client, ids, now.timestamp()).await?;
+    }
+    if !errors.is_empty() {
+        return Err(anyhow!("failed to deliver digest:\n{}", errors.join("\n")));
     }
 
     Ok(true)
@@ -179,24 +447,98 @@ pub async fn notify(data: &WishListData) -> Result<bool> {
 
 #[cfg(test)]
 mod tests {
-    extern crate dotenv;
-
     use super::*;
+    use crate::config::Source;
+    use crate::context::fakes::{FakeFetcher, FakeNotifier, TestContext};
     use db_client::prisma::ebook_in_wish_list::Data as EBookInWishListData;
-    use dotenv::dotenv;
 
     #[tokio::test]
     async fn can_send_alert() {
-        dotenv().ok();
+        let test = TestContext::new(FakeFetcher::default());
+        let ctx = test.ctx();
+        let actual = send_alert_message(&ctx, "unit-test").await.unwrap();
+        assert_eq!(actual, true);
+
+        let sent = test.notifier.sent();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].0, test.config.discord.alert_chanel);
+        assert_eq!(sent[0].1["content"], "unit-test");
+    }
 
-        let actual = send_alert_message("unit-test").await.unwrap();
-        assert_eq!(actual, true)
+    #[test]
+    fn test_digest_from() {
+        let config = DiscordConfig {
+            bot_name: "bot".to_string(),
+            avatar_url: "https://example.com/avatar.png".to_string(),
+            alert_chanel: "https://example.com/alert".to_string(),
+            sale_chanel: "https://example.com/sale".to_string(),
+        };
+        let queued_helper = |id: &str, destination: &str, price: &str| {
+            let embed = EmbedItem {
+                title: "title".to_string(),
+                url: "https://example.com/dp/B00XV8YCJI".to_string(),
+                color: EmbedColor::Green,
+                fields: vec![("金額".to_string(), price.to_string())],
+            };
+            QueuedNotificationData {
+                id: id.to_string(),
+                destination: destination.to_string(),
+                content: "manga のセール情報".to_string(),
+                embed: serde_json::to_string(&embed).unwrap(),
+                queued_at: 0,
+                delivered_at: None,
+            }
+        };
+        let queued = vec![
+            queued_helper("1", "https://example.com/a", "¥500"),
+            queued_helper("2", "https://example.com/a", "¥400"),
+            queued_helper("3", "https://example.com/b", "¥500"),
+        ];
+
+        let actual = digest_from(&config, &queued, Locale::Ja);
+        assert_eq!(actual.len(), 2);
+        assert_eq!(actual[0].to, vec!["https://example.com/a"]);
+        assert_eq!(actual[0].content, "manga のセール情報 (まとめ)");
+        assert_eq!(actual[0].embeds.len(), 1);
+        assert_eq!(actual[0].embeds[0].fields[0].1, "¥400");
+        assert_eq!(actual[1].to, vec!["https://example.com/b"]);
     }
 
     #[tokio::test]
-    async fn test_notify() {
-        dotenv().ok();
+    async fn test_send_continues_after_failure() {
+        let notifier = FakeNotifier {
+            failing: vec!["https://example.com/b".to_string()],
+            ..Default::default()
+        };
+        let notification = SaleNotification {
+            to: vec![
+                "https://example.com/a".to_string(),
+                "https://example.com/b".to_string(),
+                "https://example.com/c".to_string(),
+            ],
+            from_user_name: "bot".to_string(),
+            from_avatar_url: "https://example.com/avatar.png".to_string(),
+            content: "content".to_string(),
+            embeds: vec![EmbedItem {
+                title: "title".to_string(),
+                url: "https://example.com/dp/B00XV8YCJI".to_string(),
+                color: EmbedColor::Green,
+                fields: vec![],
+            }],
+        };
+
+        let actual = send(&notifier, &notification).await;
+        assert!(actual.is_err());
+        let sent = notifier
+            .sent()
+            .into_iter()
+            .map(|(to, _)| to)
+            .collect::<Vec<_>>();
+        assert_eq!(sent, vec!["https://example.com/a", "https://example.com/c"]);
+    }
 
+    #[test]
+    fn test_render() {
         let snapshot = EBookSnapShotData {
             id: "".to_string(),
             ebook: None,
@@ -206,8 +548,8 @@ mod tests {
             price: 42.0,
             discount: None,
             discount_rate: None,
-            points: 1.0,
-            points_rate: 0.10,
+            points: 13.0,
+            points_rate: 30.0,
         };
 
         let ebook = EBookData {
@@ -217,9 +559,14 @@ mod tests {
             price: 42.0,
             snapshots: Some(vec![snapshot]),
             ebook_in_wish_list: None,
+            watched: None,
+            archived_at: None,
         };
 
         let ebook_in_wish_list = EBookInWishListData {
+            id: "id".to_string(),
+            added_at: 0,
+            removed_at: None,
             wish_list: None,
             wish_list_id: "id".to_string(),
             ebook: Some(Box::new(ebook)),
@@ -232,8 +579,35 @@ mod tests {
             scraped_at: 0,
             title: "title".to_string(),
             ebook_in_wish_list: Some(vec![ebook_in_wish_list]),
+            sale_threshold: None,
+            notification_rules: None,
+            destinations: None,
+            user: None,
+            user_id: None,
         };
-        let actual = notify(&data).await.unwrap();
-        assert_eq!(actual, true)
+        let source = Source::from_toml(
+            r#"
+[database]
+url = "postgresql://localhost:5432/crate"
+
+[discord]
+bot_name = "bot"
+avatar_url = "https://example.com/avatar.png"
+alert_chanel = "https://example.com/alert"
+sale_chanel = "https://example.com/sale"
+"#,
+        )
+        .unwrap();
+        let config = Config::from_source(&source).unwrap();
+        let actual = render(&config, &data, &[]).unwrap();
+        assert_eq!(actual.len(), 1);
+        assert_eq!(actual[0].messages.len(), 1);
+        assert_eq!(actual[0].embeds[0].color, EmbedColor::Yellow);
+        assert!(actual[0]
+            .table()
+            .contains("[Yellow] title https://example.com"));
+
+        let json = serde_json::to_value(&actual[0]).unwrap();
+        assert_eq!(json["messages"][0]["embeds"][0]["title"], "title");
     }
 }
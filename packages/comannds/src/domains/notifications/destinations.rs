@@ -0,0 +1,129 @@
+use anyhow::{anyhow, Result};
+use db_client::prisma::wish_list::Data as WishListData;
+use std::collections::BTreeMap;
+
+pub const NOTIFIER_PREFIX: &str = "DISCORD_NOTIFIER_";
+
+/// 通知先の ID を環境変数のキーに使える形にそろえる
+pub fn normalize(id: &str) -> String {
+    id.trim().to_uppercase().replace(['-', '.', ' '], "_")
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Destination {
+    Webhook(String),
+    Notifier(String),
+}
+
+impl Destination {
+    pub fn parse<T: AsRef<str>>(value: T) -> Destination {
+        let value = value.as_ref().trim();
+        if value.starts_with("https://") || value.starts_with("http://") {
+            Destination::Webhook(value.to_string())
+        } else {
+            Destination::Notifier(value.to_string())
+        }
+    }
+
+    /// notifiers は Config::notifiers (正規化した ID と webhook URL)
+    pub fn resolve(&self, notifiers: &BTreeMap<String, String>) -> Result<String> {
+        match self {
+            Destination::Webhook(url) => Ok(url.clone()),
+            Destination::Notifier(id) => notifiers.get(&normalize(id)).cloned().ok_or(anyhow!(
+                "unknown notifier: {} ({}{})",
+                id,
+                NOTIFIER_PREFIX,
+                normalize(id)
+            )),
+        }
+    }
+}
+
+pub fn route(
+    destinations: &[Destination],
+    notifiers: &BTreeMap<String, String>,
+    fallback: &str,
+) -> Vec<String> {
+    let mut urls: Vec<String> = vec![];
+    for destination in destinations {
+        match destination.resolve(notifiers) {
+            Ok(url) if !urls.contains(&url) => urls.push(url),
+            Ok(_) => {}
+            Err(e) => warn!("{}", e),
+        }
+    }
+    if urls.is_empty() {
+        return vec![fallback.to_string()];
+    }
+    urls
+}
+
+pub fn of_wish_list(data: &WishListData) -> Vec<Destination> {
+    data.destinations
+        .iter()
+        .flatten()
+        .map(|x| Destination::parse(&x.destination))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            Destination::parse("https://discord.com/api/webhooks/1/a"),
+            Destination::Webhook(String::from("https://discord.com/api/webhooks/1/a"))
+        );
+        assert_eq!(
+            Destination::parse(" manga "),
+            Destination::Notifier(String::from("manga"))
+        );
+    }
+
+    fn notifiers() -> BTreeMap<String, String> {
+        BTreeMap::from([(
+            "TEST_RESOLVE".to_string(),
+            "https://example.com/resolve".to_string(),
+        )])
+    }
+
+    #[test]
+    fn test_resolve_notifier() {
+        assert_eq!(
+            Destination::parse("test-resolve")
+                .resolve(&notifiers())
+                .unwrap(),
+            "https://example.com/resolve"
+        );
+        assert!(Destination::parse("test-missing")
+            .resolve(&notifiers())
+            .is_err());
+    }
+
+    #[test]
+    fn test_route_falls_back() {
+        assert_eq!(route(&[], &notifiers(), "fallback"), vec!["fallback"]);
+        assert_eq!(
+            route(
+                &[Destination::parse("test-route-missing")],
+                &notifiers(),
+                "fallback"
+            ),
+            vec!["fallback"]
+        );
+        assert_eq!(
+            route(
+                &[
+                    Destination::parse("https://example.com/a"),
+                    Destination::parse("https://example.com/a"),
+                    Destination::parse("https://example.com/b"),
+                ],
+                &notifiers(),
+                "fallback"
+            ),
+            vec!["https://example.com/a", "https://example.com/b"]
+        );
+    }
+}
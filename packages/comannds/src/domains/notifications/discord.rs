@@ -0,0 +1,132 @@
+use crate::context::Notifier;
+use anyhow::{anyhow, Result};
+use futures::future::BoxFuture;
+use reqwest::header::RETRY_AFTER;
+use reqwest::{Response, StatusCode};
+use serde::Deserialize;
+use std::time::Duration;
+use webhook::models::Message;
+
+// https://discord.com/developers/docs/resources/channel#embed-object-embed-limits
+pub const MAX_CONTENT: usize = 2000;
+pub const MAX_EMBEDS: usize = 10;
+pub const MAX_TITLE: usize = 256;
+pub const MAX_FIELD_NAME: usize = 256;
+pub const MAX_FIELD_VALUE: usize = 1024;
+pub const MAX_TOTAL: usize = 6000;
+
+const MAX_RETRIES: usize = 5;
+
+pub fn truncate<T: AsRef<str>>(text: T, max: usize) -> String {
+    let text = text.as_ref();
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    let mut truncated = text.chars().take(max - 1).collect::<String>();
+    truncated.push('…');
+    truncated
+}
+
+/// 1 メッセージあたりの embed 数と文字数の上限を超えないように分割する
+pub fn chunk<T, F>(items: &[T], size_of: F) -> Vec<&[T]>
+where
+    F: Fn(&T) -> usize,
+{
+    let mut chunks = vec![];
+    let mut start = 0;
+    let mut total = 0;
+    for (i, item) in items.iter().enumerate() {
+        let size = size_of(item);
+        if i > start && (i - start >= MAX_EMBEDS || total + size > MAX_TOTAL) {
+            chunks.push(&items[start..i]);
+            start = i;
+            total = 0;
+        }
+        total += size;
+    }
+    if start < items.len() {
+        chunks.push(&items[start..]);
+    }
+    chunks
+}
+
+#[derive(Deserialize, Debug)]
+struct RateLimited {
+    retry_after: f64,
+}
+
+async fn retry_after(response: Response) -> Duration {
+    let header = response
+        .headers()
+        .get(RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<f64>().ok());
+    let body = response
+        .json::<RateLimited>()
+        .await
+        .ok()
+        .map(|body| body.retry_after);
+    Duration::from_secs_f64(body.or(header).unwrap_or(1.0).max(0.0))
+}
+
+pub async fn send(url: &str, message: &Message) -> Result<()> {
+    let client = reqwest::Client::new();
+    for _ in 0..MAX_RETRIES {
+        let response = client.post(url).json(message).send().await?;
+        if response.status() == StatusCode::TOO_MANY_REQUESTS {
+            let wait = retry_after(response).await;
+            warn!("rate limited by discord, retry after {:?}", wait);
+            tokio::time::sleep(wait).await;
+            continue;
+        }
+        response.error_for_status()?;
+        return Ok(());
+    }
+    Err(anyhow!("rate limited by discord {} times", MAX_RETRIES))
+}
+
+pub struct Discord;
+
+impl Notifier for Discord {
+    fn send<'a>(&'a self, url: &'a str, message: &'a Message) -> BoxFuture<'a, Result<()>> {
+        Box::pin(send(url, message))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate("abc", 3), "abc");
+        assert_eq!(truncate("abcd", 3), "ab…");
+        assert_eq!(truncate("あいうえお", 4), "あいう…");
+    }
+
+    #[test]
+    fn test_chunk_by_count() {
+        let items = vec![1; 25];
+        let actual = chunk(&items, |_| 1)
+            .iter()
+            .map(|c| c.len())
+            .collect::<Vec<_>>();
+        assert_eq!(actual, vec![10, 10, 5]);
+    }
+
+    #[test]
+    fn test_chunk_by_total_size() {
+        let items = vec![2500; 5];
+        let actual = chunk(&items, |size| *size)
+            .iter()
+            .map(|c| c.len())
+            .collect::<Vec<_>>();
+        assert_eq!(actual, vec![2, 2, 1]);
+    }
+
+    #[test]
+    fn test_chunk_empty() {
+        let items: Vec<usize> = vec![];
+        assert!(chunk(&items, |size| *size).is_empty());
+    }
+}
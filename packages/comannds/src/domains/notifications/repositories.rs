@@ -0,0 +1,93 @@
+use anyhow::Result;
+use db_client::prisma::queued_notification::Data as QueuedNotificationData;
+use db_client::prisma::{queued_notification, PrismaClient};
+use prisma_client_rust::Direction::Asc;
+
+pub async fn enqueue(
+    client: &PrismaClient,
+    destination: &str,
+    content: &str,
+    embeds: &[String],
+    queued_at: i64,
+) -> Result<()> {
+    let rows = embeds
+        .iter()
+        .map(|embed| {
+            (
+                destination.to_string(),
+                content.to_string(),
+                embed.clone(),
+                queued_at,
+                vec![],
+            )
+        })
+        .collect::<Vec<_>>();
+    client
+        .queued_notification()
+        .create_many(rows)
+        .exec()
+        .await?;
+    Ok(())
+}
+
+pub async fn select_queued(client: &PrismaClient) -> Result<Vec<QueuedNotificationData>> {
+    let queued = client
+        .queued_notification()
+        .find_many(vec![queued_notification::delivered_at::equals(None)])
+        .order_by(queued_notification::queued_at::order(Asc))
+        .exec()
+        .await?;
+    Ok(queued)
+}
+
+pub async fn mark_delivered(
+    client: &PrismaClient,
+    ids: Vec<String>,
+    delivered_at: i64,
+) -> Result<()> {
+    client
+        .queued_notification()
+        .update_many(
+            vec![queued_notification::id::in_vec(ids)],
+            vec![queued_notification::delivered_at::set(Some(delivered_at))],
+        )
+        .exec()
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use db_client::prisma;
+
+    #[tokio::test]
+    async fn test_enqueue_and_mark_delivered() {
+        dotenv::dotenv().ok();
+
+        let client = prisma::new_client().await.unwrap();
+        let now = Utc::now().timestamp();
+        enqueue(
+            &client,
+            "https://example.com",
+            "unit-test",
+            &[String::from("{}")],
+            now,
+        )
+        .await
+        .unwrap();
+
+        let queued = select_queued(&client).await.unwrap();
+        let ids = queued
+            .iter()
+            .filter(|x| x.content == "unit-test")
+            .map(|x| x.id.clone())
+            .collect::<Vec<_>>();
+        assert!(!ids.is_empty());
+
+        mark_delivered(&client, ids.clone(), now).await.unwrap();
+        let actual = select_queued(&client).await.unwrap();
+        assert!(actual.iter().all(|x| !ids.contains(&x.id)));
+    }
+}
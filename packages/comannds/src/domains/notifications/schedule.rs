@@ -0,0 +1,246 @@
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, Datelike, Duration, FixedOffset, TimeZone, Timelike, Utc, Weekday};
+use serde::Deserialize;
+
+use crate::config::Source;
+
+pub fn jst() -> FixedOffset {
+    FixedOffset::east_opt(9 * 3600).unwrap()
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct QuietHours {
+    pub start: u32,
+    pub end: u32,
+}
+
+impl QuietHours {
+    fn parse(value: &str) -> Result<QuietHours> {
+        let (start, end) = value
+            .split_once('-')
+            .ok_or(anyhow!("quiet hours must be like 23-7 but got {}", value))?;
+        let start = start.trim().parse::<u32>()?;
+        let end = end.trim().parse::<u32>()?;
+        if start > 23 || end > 23 {
+            bail!("quiet hours must be between 0 and 23 but got {}", value);
+        }
+        Ok(QuietHours { start, end })
+    }
+
+    pub fn contains(&self, hour: u32) -> bool {
+        if self.start <= self.end {
+            self.start <= hour && hour < self.end
+        } else {
+            hour >= self.start || hour < self.end
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Digest {
+    Off,
+    Daily,
+    Weekly(Weekday),
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct Config {
+    quiet_hours: Option<String>,
+    digest: Option<String>,
+    digest_hour: Option<u32>,
+    digest_weekday: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Schedule {
+    pub quiet_hours: Option<QuietHours>,
+    pub digest: Digest,
+    pub digest_hour: u32,
+}
+
+impl Default for Schedule {
+    fn default() -> Self {
+        Schedule {
+            quiet_hours: None,
+            digest: Digest::Off,
+            digest_hour: 8,
+        }
+    }
+}
+
+impl Schedule {
+    fn from_config(config: Config) -> Result<Schedule> {
+        let quiet_hours = config
+            .quiet_hours
+            .as_deref()
+            .map(QuietHours::parse)
+            .transpose()?;
+        let weekday = config
+            .digest_weekday
+            .as_deref()
+            .unwrap_or("mon")
+            .parse::<Weekday>()
+            .map_err(|_| anyhow!("invalid digest weekday: {:?}", config.digest_weekday))?;
+        let digest = match config.digest.as_deref().unwrap_or("off") {
+            "off" => Digest::Off,
+            "daily" => Digest::Daily,
+            "weekly" => Digest::Weekly(weekday),
+            other => bail!("digest must be off, daily or weekly but got {}", other),
+        };
+        let digest_hour = config.digest_hour.unwrap_or(8);
+        if digest_hour > 23 {
+            bail!(
+                "digest hour must be between 0 and 23 but got {}",
+                digest_hour
+            );
+        }
+        Ok(Schedule {
+            quiet_hours,
+            digest,
+            digest_hour,
+        })
+    }
+
+    pub fn from_source(source: &Source) -> Result<Schedule> {
+        Schedule::from_config(source.section("NOTIFICATION_")?)
+    }
+
+    fn is_quiet(&self, now: &DateTime<FixedOffset>) -> bool {
+        self.quiet_hours
+            .map(|quiet_hours| quiet_hours.contains(now.hour()))
+            .unwrap_or(false)
+    }
+
+    pub fn should_queue(&self, now: DateTime<Utc>) -> bool {
+        self.digest != Digest::Off || self.is_quiet(&now.with_timezone(&jst()))
+    }
+
+    // 直近の配信予定時刻
+    fn latest_digest_at(&self, now: &DateTime<FixedOffset>) -> Option<DateTime<FixedOffset>> {
+        let today = now.date_naive().and_hms_opt(self.digest_hour, 0, 0)?;
+        let today = jst().from_local_datetime(&today).single()?;
+        let at = match self.digest {
+            Digest::Off => return None,
+            Digest::Daily => today,
+            Digest::Weekly(weekday) => {
+                let days =
+                    (7 + now.weekday().num_days_from_monday() - weekday.num_days_from_monday()) % 7;
+                today - Duration::days(days as i64)
+            }
+        };
+        let period = match self.digest {
+            Digest::Weekly(_) => Duration::days(7),
+            _ => Duration::days(1),
+        };
+        Some(if at <= *now { at } else { at - period })
+    }
+
+    pub fn is_due(&self, now: DateTime<Utc>, oldest_queued_at: i64) -> bool {
+        let now = now.with_timezone(&jst());
+        if self.is_quiet(&now) {
+            return false;
+        }
+        match self.latest_digest_at(&now) {
+            Some(at) => oldest_queued_at < at.timestamp(),
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn jst_helper(y: i32, m: u32, d: u32, h: u32, min: u32) -> DateTime<Utc> {
+        jst()
+            .with_ymd_and_hms(y, m, d, h, min, 0)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_quiet_hours_contains() {
+        let overnight = QuietHours::parse("23-7").unwrap();
+        assert!(overnight.contains(23));
+        assert!(overnight.contains(0));
+        assert!(overnight.contains(6));
+        assert!(!overnight.contains(7));
+        assert!(!overnight.contains(22));
+
+        let daytime = QuietHours::parse("9-18").unwrap();
+        assert!(daytime.contains(9));
+        assert!(!daytime.contains(18));
+        assert!(QuietHours::parse("25-7").is_err());
+        assert!(QuietHours::parse("23").is_err());
+    }
+
+    #[test]
+    fn test_quiet_hours_queue_and_deliver_after_window() {
+        let schedule = Schedule {
+            quiet_hours: Some(QuietHours { start: 23, end: 7 }),
+            ..Schedule::default()
+        };
+        let queued_at = jst_helper(2023, 3, 1, 23, 30);
+        assert!(schedule.should_queue(queued_at));
+        assert!(!schedule.should_queue(jst_helper(2023, 3, 1, 12, 0)));
+        assert!(!schedule.is_due(jst_helper(2023, 3, 2, 6, 59), queued_at.timestamp()));
+        assert!(schedule.is_due(jst_helper(2023, 3, 2, 7, 0), queued_at.timestamp()));
+    }
+
+    #[test]
+    fn test_daily_digest() {
+        let schedule = Schedule {
+            digest: Digest::Daily,
+            digest_hour: 8,
+            ..Schedule::default()
+        };
+        let queued_at = jst_helper(2023, 3, 1, 10, 0).timestamp();
+        assert!(schedule.should_queue(jst_helper(2023, 3, 1, 10, 0)));
+        assert!(!schedule.is_due(jst_helper(2023, 3, 1, 23, 0), queued_at));
+        assert!(!schedule.is_due(jst_helper(2023, 3, 2, 7, 59), queued_at));
+        assert!(schedule.is_due(jst_helper(2023, 3, 2, 8, 0), queued_at));
+    }
+
+    #[test]
+    fn test_weekly_digest() {
+        let schedule = Schedule {
+            digest: Digest::Weekly(Weekday::Sat),
+            digest_hour: 9,
+            ..Schedule::default()
+        };
+        // 2023/03/01 は水曜日
+        let queued_at = jst_helper(2023, 3, 1, 10, 0).timestamp();
+        assert!(!schedule.is_due(jst_helper(2023, 3, 3, 12, 0), queued_at));
+        assert!(!schedule.is_due(jst_helper(2023, 3, 4, 8, 0), queued_at));
+        assert!(schedule.is_due(jst_helper(2023, 3, 4, 9, 0), queued_at));
+        assert!(schedule.is_due(jst_helper(2023, 3, 6, 9, 0), queued_at));
+    }
+
+    #[test]
+    fn test_from_config() {
+        let config = Config {
+            quiet_hours: Some("22-6".to_string()),
+            digest: Some("weekly".to_string()),
+            digest_hour: Some(20),
+            digest_weekday: Some("fri".to_string()),
+        };
+        let actual = Schedule::from_config(config).unwrap();
+        assert_eq!(
+            actual,
+            Schedule {
+                quiet_hours: Some(QuietHours { start: 22, end: 6 }),
+                digest: Digest::Weekly(Weekday::Fri),
+                digest_hour: 20,
+            }
+        );
+        assert_eq!(
+            Schedule::from_config(Config::default()).unwrap(),
+            Schedule::default()
+        );
+        let invalid = Config {
+            digest: Some("hourly".to_string()),
+            ..Config::default()
+        };
+        assert!(Schedule::from_config(invalid).is_err());
+    }
+}
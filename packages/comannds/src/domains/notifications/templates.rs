@@ -0,0 +1,255 @@
+use crate::config::Source;
+use crate::domains::rules::evaluator::Facts;
+use crate::locale::Locale;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tera::{Context, Tera, Value};
+
+pub const FIELDS: [&str; 4] = ["price", "discount_rate", "points_rate", "updated_at"];
+
+const NAMES: [&str; 9] = [
+    "content",
+    "price_name",
+    "price",
+    "discount_rate_name",
+    "discount_rate",
+    "points_rate_name",
+    "points_rate",
+    "updated_at_name",
+    "updated_at",
+];
+
+fn defaults(locale: Locale) -> [(&'static str, &'static str); 9] {
+    let [price, discount_rate, points_rate, updated_at] = locale.field_names();
+    [
+        ("content", locale.sale_title()),
+        ("price_name", price),
+        ("price", "{{ price | yen }}"),
+        ("discount_rate_name", discount_rate),
+        ("discount_rate", "{{ discount_rate | fixed(digits=2) }}%"),
+        ("points_rate_name", points_rate),
+        ("points_rate", "{{ points_rate | fixed(digits=2) }}%"),
+        ("updated_at_name", updated_at),
+        ("updated_at", "{{ updated_at }}"),
+    ]
+}
+
+#[derive(Serialize, Debug, PartialEq, Clone)]
+pub struct ContentContext {
+    pub wish_list: String,
+    pub rule: Option<String>,
+}
+
+#[derive(Serialize, Debug, PartialEq, Clone)]
+pub struct ItemContext {
+    #[serde(flatten)]
+    pub facts: Facts,
+    pub effective_price: f64,
+    pub url: String,
+    pub updated_at: String,
+}
+
+impl ItemContext {
+    pub fn new(facts: Facts, url: String, updated_at: String) -> ItemContext {
+        ItemContext {
+            effective_price: facts.price - facts.points,
+            facts,
+            url,
+            updated_at,
+        }
+    }
+}
+
+fn fixed(value: &Value, args: &HashMap<String, Value>) -> tera::Result<Value> {
+    let number = value
+        .as_f64()
+        .ok_or_else(|| tera::Error::msg("fixed filter expects a number"))?;
+    let digits = args.get("digits").and_then(|d| d.as_u64()).unwrap_or(2) as usize;
+    Ok(Value::String(format!("{:.*}", digits, number)))
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct Config {
+    template_dir: Option<PathBuf>,
+}
+
+pub struct Templates {
+    tera: Tera,
+}
+
+impl Templates {
+    // dir に <name>.tera があればデフォルトを上書きする
+    pub fn load<P: AsRef<Path>>(dir: Option<P>, locale: Locale) -> Result<Templates> {
+        let mut tera = Tera::default();
+        tera.register_filter("fixed", fixed);
+        tera.register_filter(
+            "yen",
+            move |value: &Value, _: &HashMap<String, Value>| -> tera::Result<Value> {
+                let number = value
+                    .as_f64()
+                    .ok_or_else(|| tera::Error::msg("yen filter expects a number"))?;
+                Ok(Value::String(locale.yen(number)))
+            },
+        );
+        for (name, template) in defaults(locale) {
+            let path = dir
+                .as_ref()
+                .map(|dir| dir.as_ref().join(format!("{}.tera", name)));
+            match path.filter(|path| path.exists()) {
+                Some(path) => {
+                    let template = fs::read_to_string(&path)?;
+                    tera.add_raw_template(name, template.trim_end_matches('\n'))
+                        .map_err(|e| anyhow!("invalid template {:?}: {:?}", path, e))?
+                }
+                None => tera.add_raw_template(name, template)?,
+            }
+        }
+        Ok(Templates { tera })
+    }
+
+    pub fn from_source(source: &Source, locale: Locale) -> Result<Templates> {
+        let config = source.section::<Config>("NOTIFICATION_")?;
+        Templates::load(config.template_dir, locale)
+    }
+
+    pub fn render<T: Serialize>(&self, name: &str, context: &T) -> Result<String> {
+        let context = Context::from_serialize(context)?;
+        self.tera
+            .render(name, &context)
+            .map_err(|e| anyhow!("can not render template {}: {:?}", name, e))
+    }
+
+    pub fn render_content(&self, context: &ContentContext) -> Result<String> {
+        self.render("content", context)
+    }
+
+    pub fn render_fields(&self, context: &ItemContext) -> Result<Vec<(String, String)>> {
+        FIELDS
+            .iter()
+            .map(|field| {
+                let name = self.render(format!("{}_name", field).as_str(), context)?;
+                let value = self.render(field, context)?;
+                Ok((name, value))
+            })
+            .collect()
+    }
+}
+
+impl Templates {
+    pub fn render_sample(&self) -> Result<Vec<(&'static str, String)>> {
+        let (content, item) = sample();
+        NAMES
+            .iter()
+            .map(|name| {
+                let rendered = match *name {
+                    "content" => self.render(name, &content)?,
+                    _ => self.render(name, &item)?,
+                };
+                Ok((*name, rendered))
+            })
+            .collect()
+    }
+}
+
+pub fn sample() -> (ContentContext, ItemContext) {
+    let content = ContentContext {
+        wish_list: String::from("do_not_delete"),
+        rule: None,
+    };
+    let facts = Facts {
+        price: 1000.0,
+        points: 300.0,
+        points_rate: 30.0,
+        discount: 100.0,
+        discount_rate: 10.0,
+        title: String::from("サンプルの本"),
+        ebook_id: String::from("B00XV8YCJI"),
+        wish_list: String::from("do_not_delete"),
+        wish_list_id: String::from("2BDAPI9RQ09E9"),
+    };
+    let item = ItemContext::new(
+        facts,
+        String::from("https://www.amazon.co.jp/dp/B00XV8YCJI"),
+        String::from("2023/03/01 09:00:00 +09:00"),
+    );
+    (content, item)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn test_render_defaults() {
+        let templates = Templates::load(None::<PathBuf>, Locale::Ja).unwrap();
+        let (content, item) = sample();
+
+        assert_eq!(
+            templates.render_content(&content).unwrap(),
+            "do_not_delete のセール情報"
+        );
+        let with_rule = ContentContext {
+            rule: Some(String::from("manga")),
+            ..content
+        };
+        assert_eq!(
+            templates.render_content(&with_rule).unwrap(),
+            "do_not_delete のセール情報 (manga)"
+        );
+        assert_eq!(
+            templates.render_fields(&item).unwrap(),
+            vec![
+                (String::from("金額"), String::from("1,000円")),
+                (String::from("値引き率"), String::from("10.00%")),
+                (String::from("ポイント還元率"), String::from("30.00%")),
+                (
+                    String::from("更新日"),
+                    String::from("2023/03/01 09:00:00 +09:00")
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_render_english_defaults() {
+        let templates = Templates::load(None::<PathBuf>, Locale::En).unwrap();
+        let (content, item) = sample();
+
+        assert_eq!(
+            templates.render_content(&content).unwrap(),
+            "Sales in do_not_delete"
+        );
+        assert_eq!(
+            templates.render_fields(&item).unwrap()[0],
+            (String::from("Price"), String::from("¥1,000"))
+        );
+    }
+
+    fn dir(test: &str) -> std::path::PathBuf {
+        env::temp_dir().join(format!("tsundoku-{}-{}", test, std::process::id()))
+    }
+
+    #[test]
+    fn test_load_overrides_from_dir() {
+        let dir = dir("test-load-overrides-from-dir");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("price.tera"), "{{ effective_price }} 円\n").unwrap();
+
+        let templates = Templates::load(Some(&dir), Locale::Ja).unwrap();
+        let (_, item) = sample();
+        assert_eq!(templates.render("price", &item).unwrap(), "700 円");
+        assert_eq!(templates.render("price_name", &item).unwrap(), "金額");
+
+        let sample = templates.render_sample().unwrap();
+        assert_eq!(sample.len(), 9);
+        assert_eq!(sample[2], ("price", String::from("700 円")));
+
+        fs::write(dir.join("price.tera"), "{{ price").unwrap();
+        assert!(Templates::load(Some(&dir), Locale::Ja).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
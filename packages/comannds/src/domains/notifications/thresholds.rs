@@ -0,0 +1,299 @@
+use anyhow::{anyhow, Result};
+use db_client::prisma::ebook_snapshot::Data as EBookSnapShotData;
+use db_client::prisma::sale_threshold::Data as SaleThresholdData;
+use serde::Deserialize;
+
+use crate::config::Source;
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone)]
+pub enum Tier {
+    None,
+    Low,
+    Middle,
+    High,
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Tiers {
+    pub low: f64,
+    pub middle: f64,
+    pub high: f64,
+}
+
+impl Default for Tiers {
+    fn default() -> Self {
+        Tiers {
+            low: 20.0,
+            middle: 30.0,
+            high: 35.0,
+        }
+    }
+}
+
+impl TryFrom<Vec<f64>> for Tiers {
+    type Error = anyhow::Error;
+
+    fn try_from(value: Vec<f64>) -> Result<Self> {
+        match value.as_slice() {
+            [low, middle, high] if low <= middle && middle <= high => Ok(Tiers {
+                low: *low,
+                middle: *middle,
+                high: *high,
+            }),
+            _ => Err(anyhow!(
+                "tiers must be 3 ascending numbers like 20,30,35 but got {:?}",
+                value
+            )),
+        }
+    }
+}
+
+impl Tiers {
+    pub fn tier(&self, rate: f64) -> Tier {
+        if rate >= self.high {
+            return Tier::High;
+        }
+        if rate >= self.middle {
+            return Tier::Middle;
+        }
+        if rate >= self.low {
+            return Tier::Low;
+        }
+        Tier::None
+    }
+
+    /// 一部だけ上書きしても昇順になっているか確かめる
+    fn overlay(&self, low: Option<f64>, middle: Option<f64>, high: Option<f64>) -> Result<Tiers> {
+        Tiers::try_from(vec![
+            low.unwrap_or(self.low),
+            middle.unwrap_or(self.middle),
+            high.unwrap_or(self.high),
+        ])
+    }
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct Config {
+    tiers: Option<Vec<f64>>,
+    discount_tiers: Option<Vec<f64>>,
+    points_tiers: Option<Vec<f64>>,
+    price_under: Option<f64>,
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Thresholds {
+    pub discount: Tiers,
+    pub points: Tiers,
+    pub price_under: Option<f64>,
+}
+
+impl Thresholds {
+    fn from_config(config: Config) -> Result<Thresholds> {
+        let tiers = config
+            .tiers
+            .map(Tiers::try_from)
+            .transpose()?
+            .unwrap_or_default();
+        let discount = config
+            .discount_tiers
+            .map(Tiers::try_from)
+            .transpose()?
+            .unwrap_or(tiers);
+        let points = config
+            .points_tiers
+            .map(Tiers::try_from)
+            .transpose()?
+            .unwrap_or(tiers);
+        Ok(Thresholds {
+            discount,
+            points,
+            price_under: config.price_under,
+        })
+    }
+
+    pub fn from_source(source: &Source) -> Result<Thresholds> {
+        Thresholds::from_config(source.section("SALE_THRESHOLD_")?)
+    }
+
+    pub fn overlay(&self, data: Option<&SaleThresholdData>) -> Result<Thresholds> {
+        let Some(data) = data else {
+            return Ok(self.clone());
+        };
+        let invalid =
+            |e: anyhow::Error| anyhow!("invalid threshold of {}: {}", data.wish_list_id, e);
+        Ok(Thresholds {
+            discount: self
+                .discount
+                .overlay(data.discount_low, data.discount_middle, data.discount_high)
+                .map_err(invalid)?,
+            points: self
+                .points
+                .overlay(data.points_low, data.points_middle, data.points_high)
+                .map_err(invalid)?,
+            price_under: data.price_under.or(self.price_under),
+        })
+    }
+
+    pub fn tier(&self, snapshot: &EBookSnapShotData) -> Tier {
+        let discount_rate = snapshot.discount_rate.unwrap_or(0.0);
+        let effective_price = snapshot.price - snapshot.points;
+
+        let by_rate = self
+            .discount
+            .tier(discount_rate)
+            .max(self.points.tier(snapshot.points_rate));
+        let by_price = match self.price_under {
+            Some(price_under) if effective_price <= price_under => Tier::Low,
+            _ => Tier::None,
+        };
+        by_rate.max(by_price)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot_helper(price: f64, points: f64, discount_rate: Option<f64>) -> EBookSnapShotData {
+        EBookSnapShotData {
+            id: "".to_string(),
+            ebook: None,
+            ebook_id: "".to_string(),
+            scraped_at: 0,
+            thumbnail_url: "".to_string(),
+            price,
+            discount: None,
+            discount_rate,
+            points,
+            points_rate: points / price * 100.0,
+        }
+    }
+
+    #[test]
+    fn test_default_tiers() {
+        let tiers = Tiers::default();
+        assert_eq!(tiers.tier(19.99), Tier::None);
+        assert_eq!(tiers.tier(20.0), Tier::Low);
+        assert_eq!(tiers.tier(30.0), Tier::Middle);
+        assert_eq!(tiers.tier(35.0), Tier::High);
+    }
+
+    #[test]
+    fn test_tiers_try_from() {
+        assert_eq!(
+            Tiers::try_from(vec![10.0, 20.0, 50.0]).unwrap(),
+            Tiers {
+                low: 10.0,
+                middle: 20.0,
+                high: 50.0
+            }
+        );
+        assert!(Tiers::try_from(vec![30.0, 20.0, 50.0]).is_err());
+        assert!(Tiers::try_from(vec![10.0, 20.0]).is_err());
+    }
+
+    #[test]
+    fn test_from_config_separates_discount_and_points() {
+        let config = Config {
+            tiers: Some(vec![10.0, 20.0, 30.0]),
+            discount_tiers: None,
+            points_tiers: Some(vec![40.0, 50.0, 60.0]),
+            price_under: Some(500.0),
+        };
+        let actual = Thresholds::from_config(config).unwrap();
+        assert_eq!(actual.discount.low, 10.0);
+        assert_eq!(actual.points.low, 40.0);
+        assert_eq!(actual.price_under, Some(500.0));
+    }
+
+    #[test]
+    fn test_tier_takes_higher_of_discount_and_points() {
+        let thresholds = Thresholds::default();
+        assert_eq!(
+            thresholds.tier(&snapshot_helper(1000.0, 0.0, Some(31.0))),
+            Tier::Middle
+        );
+        assert_eq!(
+            thresholds.tier(&snapshot_helper(1000.0, 400.0, Some(21.0))),
+            Tier::High
+        );
+        assert_eq!(
+            thresholds.tier(&snapshot_helper(1000.0, 10.0, None)),
+            Tier::None
+        );
+    }
+
+    #[test]
+    fn test_tier_by_price_under() {
+        let thresholds = Thresholds {
+            price_under: Some(500.0),
+            ..Thresholds::default()
+        };
+        assert_eq!(
+            thresholds.tier(&snapshot_helper(550.0, 50.0, None)),
+            Tier::Low
+        );
+        assert_eq!(
+            thresholds.tier(&snapshot_helper(550.0, 49.0, None)),
+            Tier::None
+        );
+        assert_eq!(
+            thresholds.tier(&snapshot_helper(400.0, 200.0, None)),
+            Tier::High
+        );
+    }
+
+    #[test]
+    fn test_overlay() {
+        let data = SaleThresholdData {
+            wish_list: None,
+            wish_list_id: "id".to_string(),
+            discount_low: Some(5.0),
+            discount_middle: None,
+            discount_high: None,
+            points_low: None,
+            points_middle: None,
+            points_high: Some(80.0),
+            price_under: Some(300.0),
+        };
+        let actual = Thresholds::default().overlay(Some(&data)).unwrap();
+        assert_eq!(
+            actual.discount,
+            Tiers {
+                low: 5.0,
+                middle: 30.0,
+                high: 35.0
+            }
+        );
+        assert_eq!(
+            actual.points,
+            Tiers {
+                low: 20.0,
+                middle: 30.0,
+                high: 80.0
+            }
+        );
+        assert_eq!(actual.price_under, Some(300.0));
+        assert_eq!(
+            Thresholds::default().overlay(None).unwrap(),
+            Thresholds::default()
+        );
+    }
+
+    #[test]
+    fn test_overlay_rejects_unordered_tiers() {
+        // 既定の 20,30,35 に low だけ 40 を重ねると昇順にならない
+        let data = SaleThresholdData {
+            wish_list: None,
+            wish_list_id: "id".to_string(),
+            discount_low: Some(40.0),
+            discount_middle: None,
+            discount_high: None,
+            points_low: None,
+            points_middle: None,
+            points_high: None,
+            price_under: None,
+        };
+        assert!(Thresholds::default().overlay(Some(&data)).is_err());
+    }
+}
@@ -0,0 +1,113 @@
+pub mod evaluator;
+pub mod parser;
+
+use anyhow::Result;
+use db_client::prisma::notification_rule::Data as NotificationRuleData;
+use evaluator::Facts;
+use parser::{parse, Expr};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Rule {
+    pub name: String,
+    pub expr: Expr,
+    pub channel: Option<String>,
+    pub user_id: Option<String>,
+    pub wish_list_id: Option<String>,
+}
+
+impl Rule {
+    pub fn new(data: &NotificationRuleData) -> Result<Rule> {
+        Ok(Rule {
+            name: data.name.clone(),
+            expr: parse(data.expression.as_str())?,
+            channel: data.channel.clone(),
+            user_id: data.user_id.clone(),
+            wish_list_id: data.wish_list_id.clone(),
+        })
+    }
+
+    /// ユーザーやウィッシュリストに紐づくルールは、そのユーザーが持つウィッシュリストにだけ適用する
+    pub fn applies_to(&self, wish_list_id: &str, owner: Option<&str>) -> bool {
+        let wish_list = match &self.wish_list_id {
+            Some(id) => id == wish_list_id,
+            None => true,
+        };
+        let user = match &self.user_id {
+            Some(id) => owner == Some(id.as_str()),
+            None => true,
+        };
+        wish_list && user
+    }
+
+    pub fn matches(&self, facts: &Facts) -> bool {
+        self.expr.eval(facts)
+    }
+}
+
+pub fn from_data(data: &[NotificationRuleData]) -> Vec<Rule> {
+    data.iter()
+        .filter_map(|rule| match Rule::new(rule) {
+            Ok(r) => Some(r),
+            Err(e) => {
+                error!("invalid rule id:{} name:{} error:{}", rule.id, rule.name, e);
+                None
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule_helper(
+        expression: &str,
+        user_id: Option<&str>,
+        wish_list_id: Option<&str>,
+    ) -> NotificationRuleData {
+        NotificationRuleData {
+            id: "id".to_string(),
+            name: "name".to_string(),
+            expression: expression.to_string(),
+            channel: None,
+            user: None,
+            user_id: user_id.map(|id| id.to_string()),
+            wish_list: None,
+            wish_list_id: wish_list_id.map(|id| id.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_from_data_skips_invalid_rules() {
+        let data = vec![
+            rule_helper("points_rate >= 50", None, None),
+            rule_helper("points_rate >=", None, None),
+        ];
+        let actual = from_data(&data);
+        assert_eq!(actual.len(), 1);
+        assert_eq!(actual[0].name, "name");
+    }
+
+    #[test]
+    fn test_applies_to() {
+        let global = Rule::new(&rule_helper("price < 100", None, None)).unwrap();
+        let scoped = Rule::new(&rule_helper("price < 100", None, Some("a"))).unwrap();
+        assert!(global.applies_to("a", None));
+        assert!(global.applies_to("b", Some("alice")));
+        assert!(scoped.applies_to("a", None));
+        assert!(!scoped.applies_to("b", None));
+    }
+
+    #[test]
+    fn test_applies_to_user() {
+        let user = Rule::new(&rule_helper("price < 100", Some("alice"), None)).unwrap();
+        assert!(user.applies_to("a", Some("alice")));
+        assert!(user.applies_to("b", Some("alice")));
+        assert!(!user.applies_to("a", Some("bob")));
+        assert!(!user.applies_to("a", None));
+
+        let both = Rule::new(&rule_helper("price < 100", Some("alice"), Some("a"))).unwrap();
+        assert!(both.applies_to("a", Some("alice")));
+        assert!(!both.applies_to("b", Some("alice")));
+    }
+}
@@ -0,0 +1,139 @@
+use crate::domains::rules::parser::{CmpOp, Expr, Field, Operand};
+use db_client::prisma::ebook::Data as EBookData;
+use db_client::prisma::ebook_snapshot::Data as EBookSnapShotData;
+use db_client::prisma::wish_list::Data as WishListData;
+use serde::Serialize;
+
+#[derive(Serialize, Debug, PartialEq, Clone, Default)]
+pub struct Facts {
+    pub price: f64,
+    pub points: f64,
+    pub points_rate: f64,
+    pub discount: f64,
+    pub discount_rate: f64,
+    pub title: String,
+    pub ebook_id: String,
+    pub wish_list: String,
+    pub wish_list_id: String,
+}
+
+impl Facts {
+    pub fn new(wish_list: &WishListData, ebook: &EBookData, snapshot: &EBookSnapShotData) -> Facts {
+        Facts {
+            price: snapshot.price,
+            points: snapshot.points,
+            points_rate: snapshot.points_rate,
+            discount: snapshot.discount.unwrap_or(0.0),
+            discount_rate: snapshot.discount_rate.unwrap_or(0.0),
+            title: ebook.title.clone(),
+            ebook_id: ebook.id.clone(),
+            wish_list: wish_list.title.clone(),
+            wish_list_id: wish_list.id.clone(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Value<'a> {
+    Number(f64),
+    Text(&'a str),
+}
+
+fn value_of<'a>(operand: &'a Operand, facts: &'a Facts) -> Value<'a> {
+    match operand {
+        Operand::Number(n) => Value::Number(*n),
+        Operand::Text(s) => Value::Text(s.as_str()),
+        Operand::Field(field) => match field {
+            Field::Price => Value::Number(facts.price),
+            Field::Points => Value::Number(facts.points),
+            Field::PointsRate => Value::Number(facts.points_rate),
+            Field::Discount => Value::Number(facts.discount),
+            Field::DiscountRate => Value::Number(facts.discount_rate),
+            Field::EffectivePrice => Value::Number(facts.price - facts.points),
+            Field::Title => Value::Text(facts.title.as_str()),
+            Field::EbookId => Value::Text(facts.ebook_id.as_str()),
+            Field::WishList => Value::Text(facts.wish_list.as_str()),
+            Field::WishListId => Value::Text(facts.wish_list_id.as_str()),
+        },
+    }
+}
+
+fn compare(lhs: Value, op: CmpOp, rhs: Value) -> bool {
+    match (lhs, rhs) {
+        (Value::Number(l), Value::Number(r)) => match op {
+            CmpOp::Eq => l == r,
+            CmpOp::Ne => l != r,
+            CmpOp::Lt => l < r,
+            CmpOp::Le => l <= r,
+            CmpOp::Gt => l > r,
+            CmpOp::Ge => l >= r,
+        },
+        (Value::Text(l), Value::Text(r)) => match op {
+            CmpOp::Eq => l == r,
+            CmpOp::Ne => l != r,
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+impl Expr {
+    pub fn eval(&self, facts: &Facts) -> bool {
+        match self {
+            Expr::Or(lhs, rhs) => lhs.eval(facts) || rhs.eval(facts),
+            Expr::And(lhs, rhs) => lhs.eval(facts) && rhs.eval(facts),
+            Expr::Not(expr) => !expr.eval(facts),
+            Expr::Compare(lhs, op, rhs) => compare(value_of(lhs, facts), *op, value_of(rhs, facts)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domains::rules::parser::parse;
+
+    fn facts_helper() -> Facts {
+        Facts {
+            price: 500.0,
+            points: 250.0,
+            points_rate: 50.0,
+            discount: 0.0,
+            discount_rate: 0.0,
+            title: "title".to_string(),
+            ebook_id: "B00XV8YCJI".to_string(),
+            wish_list: "manga".to_string(),
+            wish_list_id: "2BDAPI9RQ09E9".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_eval() {
+        let facts = facts_helper();
+        let cases = [
+            ("points_rate >= 50", true),
+            ("points_rate > 50", false),
+            ("effective_price <= 250", true),
+            ("effective_price < price", true),
+            ("wishlist = 'manga' AND discount_rate > 0", false),
+            ("wishlist != 'novel' AND NOT discount_rate > 0", true),
+            (
+                "points_rate >= 60 OR effective_price <= 300 AND wishlist = 'manga'",
+                true,
+            ),
+            (
+                "(points_rate >= 60 OR effective_price <= 300) AND wishlist = 'novel'",
+                false,
+            ),
+            ("ebook_id = \"B00XV8YCJI\"", true),
+        ];
+        for (expression, expected) in cases {
+            assert_eq!(
+                parse(expression).unwrap().eval(&facts),
+                expected,
+                "{}",
+                expression
+            );
+        }
+    }
+}
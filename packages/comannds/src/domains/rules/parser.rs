@@ -0,0 +1,333 @@
+use anyhow::{anyhow, bail, Result};
+use std::iter::Peekable;
+use std::str::Chars;
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Field {
+    Price,
+    Points,
+    PointsRate,
+    Discount,
+    DiscountRate,
+    EffectivePrice,
+    Title,
+    EbookId,
+    WishList,
+    WishListId,
+}
+
+impl Field {
+    fn from_name(name: &str) -> Option<Field> {
+        let field = match name {
+            "price" => Field::Price,
+            "points" => Field::Points,
+            "points_rate" => Field::PointsRate,
+            "discount" => Field::Discount,
+            "discount_rate" => Field::DiscountRate,
+            "effective_price" => Field::EffectivePrice,
+            "title" => Field::Title,
+            "ebook_id" => Field::EbookId,
+            "wishlist" => Field::WishList,
+            "wishlist_id" => Field::WishListId,
+            _ => return None,
+        };
+        Some(field)
+    }
+
+    fn is_text(&self) -> bool {
+        matches!(
+            self,
+            Field::Title | Field::EbookId | Field::WishList | Field::WishListId
+        )
+    }
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Operand {
+    Field(Field),
+    Number(f64),
+    Text(String),
+}
+
+impl Operand {
+    fn is_text(&self) -> bool {
+        match self {
+            Operand::Field(field) => field.is_text(),
+            Operand::Number(_) => false,
+            Operand::Text(_) => true,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Expr {
+    Or(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Compare(Operand, CmpOp, Operand),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+enum Token {
+    Number(f64),
+    Text(String),
+    Ident(String),
+    Op(CmpOp),
+    And,
+    Or,
+    Not,
+    LParen,
+    RParen,
+}
+
+fn read_while<F: Fn(char) -> bool>(chars: &mut Peekable<Chars>, f: F) -> String {
+    let mut s = String::new();
+    while let Some(&c) = chars.peek() {
+        if !f(c) {
+            break;
+        }
+        s.push(c);
+        chars.next();
+    }
+    s
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>> {
+    let mut tokens = vec![];
+    let mut chars = input.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        let token = match c {
+            c if c.is_whitespace() => {
+                chars.next();
+                continue;
+            }
+            '(' => {
+                chars.next();
+                Token::LParen
+            }
+            ')' => {
+                chars.next();
+                Token::RParen
+            }
+            '\'' | '"' => {
+                chars.next();
+                let text = read_while(&mut chars, |x| x != c);
+                if chars.next() != Some(c) {
+                    bail!("unterminated string literal: {}{}", c, text);
+                }
+                Token::Text(text)
+            }
+            '0'..='9' | '.' => {
+                let number = read_while(&mut chars, |x| x.is_ascii_digit() || x == '.');
+                Token::Number(
+                    number
+                        .parse::<f64>()
+                        .map_err(|_| anyhow!("invalid number: {}", number))?,
+                )
+            }
+            '=' | '!' | '<' | '>' | '&' | '|' => {
+                let op = read_while(&mut chars, |x| "=!<>&|".contains(x));
+                match op.as_str() {
+                    "=" | "==" => Token::Op(CmpOp::Eq),
+                    "!=" | "<>" => Token::Op(CmpOp::Ne),
+                    "<" => Token::Op(CmpOp::Lt),
+                    "<=" => Token::Op(CmpOp::Le),
+                    ">" => Token::Op(CmpOp::Gt),
+                    ">=" => Token::Op(CmpOp::Ge),
+                    "&&" => Token::And,
+                    "||" => Token::Or,
+                    "!" => Token::Not,
+                    _ => bail!("unknown operator: {}", op),
+                }
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let ident = read_while(&mut chars, |x| x.is_alphanumeric() || x == '_');
+                match ident.to_uppercase().as_str() {
+                    "AND" => Token::And,
+                    "OR" => Token::Or,
+                    "NOT" => Token::Not,
+                    _ => Token::Ident(ident.to_lowercase()),
+                }
+            }
+            _ => bail!("unexpected character: {}", c),
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+// or      := and ("OR" and)*
+// and     := not ("AND" not)*
+// not     := "NOT" not | primary
+// primary := "(" or ")" | operand op operand
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn or(&mut self) -> Result<Expr> {
+        let mut lhs = self.and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            let rhs = self.and()?;
+            lhs = Expr::Or(Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn and(&mut self) -> Result<Expr> {
+        let mut lhs = self.not()?;
+        while self.peek() == Some(&Token::And) {
+            self.next();
+            let rhs = self.not()?;
+            lhs = Expr::And(Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn not(&mut self) -> Result<Expr> {
+        if self.peek() == Some(&Token::Not) {
+            self.next();
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr> {
+        if self.peek() == Some(&Token::LParen) {
+            self.next();
+            let expr = self.or()?;
+            return match self.next() {
+                Some(Token::RParen) => Ok(expr),
+                other => Err(anyhow!("expected ')' but got {:?}", other)),
+            };
+        }
+        let lhs = self.operand()?;
+        let op = match self.next() {
+            Some(Token::Op(op)) => op,
+            other => bail!("expected comparison operator but got {:?}", other),
+        };
+        let rhs = self.operand()?;
+        if lhs.is_text() != rhs.is_text() {
+            bail!("can not compare {:?} with {:?}", lhs, rhs);
+        }
+        if lhs.is_text() && !matches!(op, CmpOp::Eq | CmpOp::Ne) {
+            bail!("text can only be compared with = or !=");
+        }
+        Ok(Expr::Compare(lhs, op, rhs))
+    }
+
+    fn operand(&mut self) -> Result<Operand> {
+        match self.next() {
+            Some(Token::Number(n)) => Ok(Operand::Number(n)),
+            Some(Token::Text(s)) => Ok(Operand::Text(s)),
+            Some(Token::Ident(name)) => Field::from_name(name.as_str())
+                .map(Operand::Field)
+                .ok_or_else(|| anyhow!("unknown field: {}", name)),
+            other => Err(anyhow!("expected field or value but got {:?}", other)),
+        }
+    }
+}
+
+pub fn parse<T: AsRef<str>>(input: T) -> Result<Expr> {
+    let tokens = tokenize(input.as_ref())?;
+    if tokens.is_empty() {
+        bail!("empty expression");
+    }
+    let mut parser = Parser { tokens, pos: 0 };
+    let expr = parser.or()?;
+    if let Some(token) = parser.peek() {
+        bail!("unexpected token: {:?}", token);
+    }
+    Ok(expr)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cmp(field: Field, op: CmpOp, value: Operand) -> Expr {
+        Expr::Compare(Operand::Field(field), op, value)
+    }
+
+    #[test]
+    fn test_parse_comparison() {
+        assert_eq!(
+            parse("points_rate >= 50").unwrap(),
+            cmp(Field::PointsRate, CmpOp::Ge, Operand::Number(50.0))
+        );
+        assert_eq!(
+            parse("wishlist = 'manga'").unwrap(),
+            cmp(
+                Field::WishList,
+                CmpOp::Eq,
+                Operand::Text("manga".to_string())
+            )
+        );
+    }
+
+    #[test]
+    fn test_parse_and_binds_tighter_than_or() {
+        let actual =
+            parse("points_rate >= 50 OR effective_price <= 300 AND wishlist = 'manga'").unwrap();
+        let expected = Expr::Or(
+            Box::new(cmp(Field::PointsRate, CmpOp::Ge, Operand::Number(50.0))),
+            Box::new(Expr::And(
+                Box::new(cmp(
+                    Field::EffectivePrice,
+                    CmpOp::Le,
+                    Operand::Number(300.0),
+                )),
+                Box::new(cmp(
+                    Field::WishList,
+                    CmpOp::Eq,
+                    Operand::Text("manga".to_string()),
+                )),
+            )),
+        );
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_parse_parens_and_not() {
+        let actual = parse("not (price > 100 || points < 10)").unwrap();
+        let expected = Expr::Not(Box::new(Expr::Or(
+            Box::new(cmp(Field::Price, CmpOp::Gt, Operand::Number(100.0))),
+            Box::new(cmp(Field::Points, CmpOp::Lt, Operand::Number(10.0))),
+        )));
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse("").is_err());
+        assert!(parse("price >").is_err());
+        assert!(parse("unknown > 1").is_err());
+        assert!(parse("price > 'a'").is_err());
+        assert!(parse("title > 'a'").is_err());
+        assert!(parse("(price > 1").is_err());
+        assert!(parse("price > 1 price").is_err());
+        assert!(parse("title = 'a").is_err());
+    }
+}
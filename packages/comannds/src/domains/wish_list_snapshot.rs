@@ -1,7 +1,8 @@
 use crate::domains::item_metadata::ItemMetaData;
+use serde::Serialize;
 use url::Url;
 
-#[derive(Debug, Eq, Ord, PartialEq, PartialOrd, Clone)]
+#[derive(Debug, Eq, Ord, PartialEq, PartialOrd, Clone, Serialize)]
 pub struct WishListSnapshot {
     pub id: String,
     pub title: String,
@@ -1,2 +1,2 @@
-mod repositories;
+pub(crate) mod repositories;
 pub mod services;
@@ -1,94 +1,220 @@
 use crate::domains::item_metadata::ItemMetaData;
 use crate::domains::wish_list_snapshot::WishListSnapshot;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use chrono::Utc;
-use db_client::prisma::ebook::Data as EbookData;
+use db_client::prisma::ebook_in_wish_list::Data as EbookInWishListData;
 use db_client::prisma::wish_list::Data as WishListData;
 use db_client::prisma::{ebook, ebook_in_wish_list, wish_list, PrismaClient};
 use headless_chrome::{Browser, Element};
+use prisma_client_rust::Direction::Asc;
 use scraper::dict::from;
+use scraper::wish_list_url::{self, WishListRef};
+use std::collections::BTreeSet;
 use url::Url;
 
-pub async fn upsert_items(client: &PrismaClient, items: &[ItemMetaData]) -> Result<Vec<EbookData>> {
-    let upsert_target = items.to_vec();
-    let item_upsert: Vec<_> = upsert_target
-        .into_iter()
+fn item_upserts<'a>(
+    client: &'a PrismaClient,
+    items: &[ItemMetaData],
+) -> Result<Vec<ebook::Upsert<'a>>> {
+    items
+        .iter()
         .map(|item| {
-            let price = item.price.parse::<f64>().unwrap();
-            client.ebook().upsert(
+            let price = item.price.parse::<f64>()?;
+            Ok(client.ebook().upsert(
                 ebook::id::equals(item.id.clone()),
                 ebook::create(
                     item.id.clone(),
                     item.url.to_string(),
-                    item.title,
+                    item.title.clone(),
                     price,
                     vec![],
                 ),
-                vec![],
-            )
+                vec![ebook::archived_at::set(None)],
+            ))
         })
-        .collect();
-    let items: Vec<_> = client._batch(item_upsert).await?;
-    Ok(items)
+        .collect()
 }
 
 pub async fn upsert_wish_list(client: &PrismaClient, snapshot: &WishListSnapshot) -> Result<()> {
-    let items = upsert_items(client, &snapshot.items).await?;
-
-    let wish_list = client
-        .wish_list()
-        .upsert(
-            wish_list::id::equals(snapshot.id.clone()),
-            wish_list::create(
-                snapshot.id.clone(),
-                snapshot.url.clone().to_string(),
-                snapshot.scraped_at,
-                snapshot.title.clone(),
-                vec![],
-            ),
-            vec![
-                wish_list::SetParam::SetUrl(snapshot.url.to_string().clone()),
-                wish_list::SetParam::SetScrapedAt(snapshot.scraped_at),
-                wish_list::SetParam::SetTitle(snapshot.title.clone()),
-            ],
-        )
-        .exec()
-        .await?;
+    let ebook_ids = snapshot
+        .items
+        .iter()
+        .map(|item| item.id.clone())
+        .collect::<Vec<_>>();
+    write_wish_list(client, snapshot, &ebook_ids).await
+}
 
-    // item と wish_list の relation を洗い替えする
-    client
+// 途中で失敗して本が 0 冊のウィッシュリストが残らないよう、1 つのトランザクションで書き込む
+async fn write_wish_list(
+    client: &PrismaClient,
+    snapshot: &WishListSnapshot,
+    ebook_ids: &[String],
+) -> Result<()> {
+    let current = client
         .ebook_in_wish_list()
-        .delete_many(vec![ebook_in_wish_list::WhereParam::WishListIdEquals(
-            wish_list.id.clone(),
-        )])
+        .find_many(vec![
+            ebook_in_wish_list::wish_list_id::equals(snapshot.id.clone()),
+            ebook_in_wish_list::removed_at::equals(None),
+        ])
         .exec()
         .await?;
-    let connect: Vec<_> = items
+    let membership = diff(&current, ebook_ids);
+
+    let connect: Vec<_> = membership
+        .added
         .into_iter()
-        .map(|item| (wish_list.id.clone(), item.id, vec![]))
+        .map(|ebook_id| (snapshot.id.clone(), ebook_id, snapshot.scraped_at, vec![]))
         .collect();
     client
-        .ebook_in_wish_list()
-        .create_many(connect)
-        .exec()
+        ._batch((
+            item_upserts(client, &snapshot.items)?,
+            client.wish_list().upsert(
+                wish_list::id::equals(snapshot.id.clone()),
+                wish_list::create(
+                    snapshot.id.clone(),
+                    snapshot.url.clone().to_string(),
+                    snapshot.scraped_at,
+                    snapshot.title.clone(),
+                    vec![],
+                ),
+                vec![
+                    wish_list::SetParam::SetUrl(snapshot.url.to_string().clone()),
+                    wish_list::SetParam::SetScrapedAt(snapshot.scraped_at),
+                    wish_list::SetParam::SetTitle(snapshot.title.clone()),
+                ],
+            ),
+            // 外されていない行は (wishListId, ebookId) で一意なので、
+            // 読んだ後に他のスナップショットが先に追加していたら飛ばす
+            client
+                .ebook_in_wish_list()
+                .create_many(connect)
+                .skip_duplicates(),
+            client.ebook_in_wish_list().update_many(
+                vec![ebook_in_wish_list::id::in_vec(membership.removed)],
+                vec![ebook_in_wish_list::removed_at::set(Some(
+                    snapshot.scraped_at,
+                ))],
+            ),
+        ))
         .await?;
 
     Ok(())
 }
 
+#[derive(Debug, PartialEq, Eq)]
+pub struct MembershipDiff {
+    /// 新しく入った ebook の ID
+    pub added: Vec<String>,
+    /// 外れた EbookInWishList の ID
+    pub removed: Vec<String>,
+}
+
+/// 今のメンバーと取得したアイテムを比べて、追加と削除を求める
+pub fn diff(current: &[EbookInWishListData], ebook_ids: &[String]) -> MembershipDiff {
+    let active = current
+        .iter()
+        .filter(|x| x.removed_at.is_none())
+        .collect::<Vec<_>>();
+    let added = ebook_ids
+        .iter()
+        .filter(|id| !active.iter().any(|x| &x.ebook_id == *id))
+        .cloned()
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+    let removed = active
+        .iter()
+        .filter(|x| !ebook_ids.contains(&x.ebook_id))
+        .map(|x| x.id.clone())
+        .collect();
+    MembershipDiff { added, removed }
+}
+
 pub async fn select_all_wish_list(client: &PrismaClient) -> Result<Vec<WishListData>> {
+    let wish_lists = client
+        .wish_list()
+        .find_many(vec![])
+        .with(
+            wish_list::ebook_in_wish_list::fetch(vec![ebook_in_wish_list::removed_at::equals(
+                None,
+            )])
+            .with(ebook_in_wish_list::ebook::fetch().with(ebook::snapshots::fetch(vec![]))),
+        )
+        .exec()
+        .await?;
+    Ok(wish_lists)
+}
+
+pub async fn select_wish_lists_with_items(client: &PrismaClient) -> Result<Vec<WishListData>> {
+    let wish_lists = client
+        .wish_list()
+        .find_many(vec![])
+        .with(wish_list::ebook_in_wish_list::fetch(vec![
+            ebook_in_wish_list::removed_at::equals(None),
+        ]))
+        .order_by(wish_list::title::order(Asc))
+        .exec()
+        .await?;
+    Ok(wish_lists)
+}
+
+/// 外れたものも含めて、すべてのメンバーの履歴を取得する
+pub async fn select_membership_history(client: &PrismaClient) -> Result<Vec<WishListData>> {
     let wish_lists = client
         .wish_list()
         .find_many(vec![])
         .with(
             wish_list::ebook_in_wish_list::fetch(vec![])
-                .with(ebook_in_wish_list::ebook::fetch().with(ebook::snapshots::fetch(vec![]))),
+                .with(ebook_in_wish_list::ebook::fetch())
+                .order_by(ebook_in_wish_list::added_at::order(Asc)),
         )
+        .order_by(wish_list::title::order(Asc))
         .exec()
         .await?;
     Ok(wish_lists)
 }
 
+pub async fn delete_wish_list(client: &PrismaClient, id: &str) -> Result<Option<WishListData>> {
+    let found = client
+        .wish_list()
+        .find_unique(wish_list::id::equals(id.to_string()))
+        .exec()
+        .await?;
+    if found.is_none() {
+        return Ok(None);
+    }
+
+    // EbookInWishList は cascade されないので先に消す
+    let (_, deleted) = client
+        ._batch((
+            client.ebook_in_wish_list().delete_many(vec![
+                ebook_in_wish_list::wish_list_id::equals(id.to_string()),
+            ]),
+            client
+                .wish_list()
+                .delete(wish_list::id::equals(id.to_string())),
+        ))
+        .await?;
+    Ok(Some(deleted))
+}
+
+/// 短縮 URL はブラウザでリダイレクト先を開いて ID を取り出す
+pub fn resolve_short_link(browser: &Browser, url: &Url) -> Result<String> {
+    let tab = browser.new_tab()?;
+    tab.navigate_to(url.as_str())?.wait_until_navigated()?;
+    let resolved = tab.get_url();
+    tab.close(true)?;
+
+    match wish_list_url::parse(resolved.as_str()) {
+        Some(WishListRef::Id(id)) => Ok(id),
+        _ => Err(anyhow!(
+            "{} does not redirect to a wishlist: {}",
+            url,
+            resolved
+        )),
+    }
+}
+
 fn create_url(id: &str) -> Result<Url> {
     let url = Url::parse("https://www.amazon.jp/hz/wishlist/ls/")?;
     let joined = url.join(id)?;
@@ -154,6 +280,7 @@ mod tests {
     use super::*;
     use chrono::Utc;
     use db_client::prisma;
+    use db_client::prisma::ebook::Data as EbookData;
     use dotenv;
     use url::Url;
 
@@ -182,7 +309,10 @@ mod tests {
         let client = prisma::new_client().await.unwrap();
         let items = items_helper();
 
-        let actual = upsert_items(&client, &items.to_vec()).await.unwrap();
+        let actual: Vec<EbookData> = client
+            ._batch(item_upserts(&client, &items).unwrap())
+            .await
+            .unwrap();
         assert_eq!(actual.len(), 2)
     }
 
@@ -203,6 +333,54 @@ mod tests {
         upsert_wish_list(&client, &expected).await.unwrap();
     }
 
+    #[tokio::test]
+    async fn test_upsert_wish_list_rolls_back() {
+        dotenv::dotenv().ok();
+
+        let client = prisma::new_client().await.unwrap();
+        let items = items_helper();
+        let before = WishListSnapshot {
+            id: String::from("2BDAPI9RQ09E9"),
+            url: Url::parse("https://www.amazon.jp/hz/wishlist/ls/2BDAPI9RQ09E9").unwrap(),
+            title: String::from("test_title"),
+            scraped_at: Utc::now().timestamp(),
+            items: items.clone(),
+        };
+        upsert_wish_list(&client, &before).await.unwrap();
+
+        // 存在しない ebook を繋ごうとして、書き込みの途中で外部キー制約に違反させる
+        let after = WishListSnapshot {
+            title: String::from("broken_title"),
+            items: items[..1].to_vec(),
+            ..before.clone()
+        };
+        let ebook_ids = vec![items[0].id.clone(), String::from("B000000000")];
+        assert!(write_wish_list(&client, &after, &ebook_ids).await.is_err());
+
+        let wish_list = client
+            .wish_list()
+            .find_unique(wish_list::id::equals(before.id.clone()))
+            .with(wish_list::ebook_in_wish_list::fetch(vec![
+                ebook_in_wish_list::removed_at::equals(None),
+            ]))
+            .exec()
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(wish_list.title, before.title);
+        let mut actual = wish_list
+            .ebook_in_wish_list
+            .unwrap()
+            .into_iter()
+            .map(|x| x.ebook_id)
+            .collect::<Vec<_>>();
+        actual.sort();
+        assert_eq!(
+            actual,
+            items.iter().map(|x| x.id.clone()).collect::<Vec<_>>()
+        );
+    }
+
     #[tokio::test]
     async fn test_select_all_wish_list() {
         dotenv::dotenv().ok();
@@ -213,6 +391,39 @@ mod tests {
         assert!(!actual.is_empty())
     }
 
+    #[test]
+    fn test_diff() {
+        let member_helper =
+            |id: &str, ebook_id: &str, removed_at: Option<i64>| EbookInWishListData {
+                id: id.to_string(),
+                wish_list: None,
+                wish_list_id: String::from("2BDAPI9RQ09E9"),
+                ebook: None,
+                ebook_id: ebook_id.to_string(),
+                added_at: 0,
+                removed_at,
+            };
+        let current = vec![
+            member_helper("1", "B09RQGMYKZ", None),
+            member_helper("2", "B09WQT2DQD", None),
+            member_helper("3", "B08L51YSLR", Some(0)),
+        ];
+        let ids = vec![
+            String::from("B09RQGMYKZ"),
+            String::from("B08L51YSLR"),
+            String::from("B08L51YSLR"),
+        ];
+
+        let actual = diff(&current, &ids);
+        assert_eq!(
+            actual,
+            MembershipDiff {
+                added: vec![String::from("B08L51YSLR")],
+                removed: vec![String::from("2")],
+            }
+        );
+    }
+
     #[test]
     fn test_create_url() {
         assert_eq!(
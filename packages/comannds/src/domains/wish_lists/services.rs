@@ -1,52 +1,287 @@
-use db_client::prisma;
+use db_client::prisma::wish_list::Data as WishListData;
 use db_client::prisma::PrismaClient;
 
+use crate::context::{Context, Fetcher};
+use crate::domains::job_runs::Summary;
+use crate::domains::wish_list_snapshot::WishListSnapshot;
 use crate::domains::wish_lists::repositories;
-use anyhow::Result;
+use crate::shutdown::Shutdown;
+use anyhow::{anyhow, Result};
+use chrono::{Duration, Utc};
 use futures::stream;
 use futures::StreamExt;
-use headless_chrome::Browser;
+use scraper::wish_list_url::{self, WishListRef};
 
-pub async fn update_wish_list(client: &PrismaClient, browser: &Browser, id: String) -> Result<()> {
-    let snapshot = repositories::get_wish_list_snapshot(browser, id.as_str())?;
-    repositories::upsert_wish_list(client, &snapshot).await?;
+pub async fn update_wish_list(ctx: &Context<'_>, id: String) -> Result<()> {
+    let snapshot = ctx.fetcher.wish_list_snapshot(id.as_str())?;
+    repositories::upsert_wish_list(ctx.client().await?, &snapshot).await?;
     Ok(())
 }
 
-pub async fn update_all_wish_list() -> Result<()> {
-    let client = prisma::new_client().await?;
-    let browser = Browser::default()?;
-    let lists = repositories::select_all_wish_list(&client).await?;
+/// 停止要求が来たら、取得中のものだけ終わらせて残りはスキップする
+pub async fn update_all_wish_list(ctx: &Context<'_>, shutdown: &Shutdown) -> Result<Summary> {
+    let config = ctx.config;
+    let lists = repositories::select_all_wish_list(ctx.client().await?).await?;
 
     let futures = lists
         .into_iter()
-        .map(|list| update_wish_list(&client, &browser, list.id))
-        .collect::<Vec<_>>();
-    let stream = stream::iter(futures).buffer_unordered(3);
-    stream.collect::<Vec<_>>().await;
-    Ok(())
+        .take_while(|_| !shutdown.is_requested())
+        .map(|list| async move {
+            tokio::time::sleep(config.scraper.request_interval()).await;
+            update_wish_list(ctx, list.id).await
+        });
+    let stream = stream::iter(futures).buffer_unordered(config.scraper.concurrency);
+    let results = stream.collect::<Vec<_>>().await;
+    Ok(Summary::from_results(results))
+}
+
+fn resolve_wish_list_id(url_or_id: &str, fetcher: &dyn Fetcher) -> Result<String> {
+    match wish_list_url::parse(url_or_id) {
+        Some(WishListRef::Id(id)) => Ok(id),
+        Some(WishListRef::ShortLink(url)) => fetcher.resolve_short_link(&url),
+        None => Err(anyhow!("{} is not a wishlist url or id", url_or_id)),
+    }
+}
+
+/// 1 件だけ取得する。save が false なら DB には書き込まない
+pub async fn snap_single_wish_list(
+    ctx: &Context<'_>,
+    url_or_id: &str,
+    save: bool,
+) -> Result<WishListSnapshot> {
+    let id = resolve_wish_list_id(url_or_id, ctx.fetcher)?;
+    let snapshot = ctx.fetcher.wish_list_snapshot(id.as_str())?;
+    if save {
+        repositories::upsert_wish_list(ctx.client().await?, &snapshot).await?;
+    }
+    Ok(snapshot)
+}
+
+/// ウィッシュリストを取得できるか確かめてから登録する
+pub async fn add_wish_list(ctx: &Context<'_>, url_or_id: &str) -> Result<WishListSnapshot> {
+    let id = resolve_wish_list_id(url_or_id, ctx.fetcher)?;
+    let snapshot = ctx.fetcher.wish_list_snapshot(id.as_str())?;
+    repositories::upsert_wish_list(ctx.client().await?, &snapshot).await?;
+    Ok(snapshot)
+}
+
+pub async fn remove_wish_list(ctx: &Context<'_>, url_or_id: &str) -> Result<bool> {
+    let id = resolve_wish_list_id(url_or_id, ctx.fetcher)?;
+    let deleted = repositories::delete_wish_list(ctx.client().await?, id.as_str()).await?;
+    Ok(deleted.is_some())
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct WishListSummary {
+    pub id: String,
+    pub title: String,
+    pub url: String,
+    pub items: usize,
+    pub scraped_at: i64,
+}
+
+pub async fn list_wish_lists(client: &PrismaClient) -> Result<Vec<WishListSummary>> {
+    let lists = repositories::select_wish_lists_with_items(client).await?;
+    let summaries = lists
+        .into_iter()
+        .map(|list| WishListSummary {
+            items: list.ebook_in_wish_list.map(|x| x.len()).unwrap_or(0),
+            id: list.id,
+            title: list.title,
+            url: list.url,
+            scraped_at: list.scraped_at,
+        })
+        .collect();
+    Ok(summaries)
+}
+
+const DAY: i64 = 24 * 60 * 60;
+
+#[derive(Debug, PartialEq)]
+pub struct MembershipReport {
+    pub id: String,
+    pub title: String,
+    /// 期間内に追加された本のタイトル
+    pub added: Vec<String>,
+    /// 期間内に外れた本のタイトル
+    pub removed: Vec<String>,
+    /// 今積まれている本の数
+    pub piled: usize,
+    /// 今積まれている本が積まれている平均日数
+    pub average_days: f64,
+    /// 一番長く積まれている本と日数
+    pub oldest: Option<(String, i64)>,
+}
+
+impl MembershipReport {
+    pub fn new(data: &WishListData, now: i64, since: i64) -> MembershipReport {
+        let members = data.ebook_in_wish_list.clone().unwrap_or_default();
+        let title_of = |ebook_id: &String| {
+            members
+                .iter()
+                .find(|x| &x.ebook_id == ebook_id)
+                .and_then(|x| x.ebook.as_ref())
+                .map(|ebook| ebook.title.clone())
+                .unwrap_or_else(|| ebook_id.clone())
+        };
+        let added = members
+            .iter()
+            .filter(|x| x.added_at >= since)
+            .map(|x| title_of(&x.ebook_id))
+            .collect();
+        let removed = members
+            .iter()
+            .filter(|x| x.removed_at.map(|at| at >= since).unwrap_or(false))
+            .map(|x| title_of(&x.ebook_id))
+            .collect();
+        let piled = members
+            .iter()
+            .filter(|x| x.removed_at.is_none())
+            .collect::<Vec<_>>();
+        let days = piled
+            .iter()
+            .map(|x| (now - x.added_at) / DAY)
+            .collect::<Vec<_>>();
+        let average_days = if days.is_empty() {
+            0.0
+        } else {
+            days.iter().sum::<i64>() as f64 / days.len() as f64
+        };
+        let oldest = piled
+            .iter()
+            .min_by_key(|x| x.added_at)
+            .map(|x| (title_of(&x.ebook_id), (now - x.added_at) / DAY));
+
+        MembershipReport {
+            id: data.id.clone(),
+            title: data.title.clone(),
+            added,
+            removed,
+            piled: piled.len(),
+            average_days,
+            oldest,
+        }
+    }
+}
+
+/// 直近 days 日の出入りと、積まれている期間を集計する
+pub async fn membership_report(client: &PrismaClient, days: i64) -> Result<Vec<MembershipReport>> {
+    let now = Utc::now();
+    let since = (now - Duration::days(days)).timestamp();
+    let lists = repositories::select_membership_history(client).await?;
+    Ok(lists
+        .iter()
+        .map(|list| MembershipReport::new(list, now.timestamp(), since))
+        .collect())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use dotenv;
+    use crate::context::fakes::{FakeFetcher, TestContext};
+    use crate::context::Scraper;
+    use db_client::prisma::ebook_in_wish_list::Data as EbookInWishListData;
+    use std::collections::HashMap;
 
     #[tokio::test]
     async fn it_works_update_wish_list() {
-        dotenv::dotenv().ok();
-        let client = prisma::new_client().await.unwrap();
-        let browser = Browser::default().unwrap();
+        let test = TestContext::new(Scraper::default());
+        let ctx = test.ctx();
 
-        update_wish_list(&client, &browser, String::from("2BDAPI9RQ09E9"))
+        update_wish_list(&ctx, String::from("2BDAPI9RQ09E9"))
             .await
             .unwrap();
     }
 
+    #[test]
+    fn test_membership_report() {
+        let member_helper =
+            |ebook_id: &str, added_at: i64, removed_at: Option<i64>| EbookInWishListData {
+                id: ebook_id.to_string(),
+                wish_list: None,
+                wish_list_id: String::from("2BDAPI9RQ09E9"),
+                ebook: None,
+                ebook_id: ebook_id.to_string(),
+                added_at,
+                removed_at,
+            };
+        let now = 100 * DAY;
+        let data = WishListData {
+            id: String::from("2BDAPI9RQ09E9"),
+            url: String::from("https://www.amazon.jp/hz/wishlist/ls/2BDAPI9RQ09E9"),
+            scraped_at: now,
+            title: String::from("manga"),
+            ebook_in_wish_list: Some(vec![
+                member_helper("B000000001", 10 * DAY, None),
+                member_helper("B000000002", 20 * DAY, Some(95 * DAY)),
+                member_helper("B000000003", 96 * DAY, None),
+            ]),
+            sale_threshold: None,
+            notification_rules: None,
+            destinations: None,
+            user: None,
+            user_id: None,
+        };
+
+        let actual = MembershipReport::new(&data, now, now - 7 * DAY);
+        assert_eq!(actual.added, vec!["B000000003"]);
+        assert_eq!(actual.removed, vec!["B000000002"]);
+        assert_eq!(actual.piled, 2);
+        assert_eq!(actual.average_days, 47.0);
+        assert_eq!(actual.oldest, Some((String::from("B000000001"), 90)));
+    }
+
+    #[test]
+    fn test_resolve_wish_list_id() {
+        let fetcher = FakeFetcher {
+            short_links: HashMap::from([(
+                "https://amzn.asia/d/abcdEFG".to_string(),
+                "2BDAPI9RQ09E9".to_string(),
+            )]),
+            ..FakeFetcher::default()
+        };
+        assert_eq!(
+            resolve_wish_list_id(
+                "amazon.co.jp/hz/wishlist/ls/2BDAPI9RQ09E9?ref_=wl_share",
+                &fetcher
+            )
+            .unwrap(),
+            "2BDAPI9RQ09E9"
+        );
+        assert_eq!(
+            resolve_wish_list_id("https://amzn.asia/d/abcdEFG", &fetcher).unwrap(),
+            "2BDAPI9RQ09E9"
+        );
+        assert!(resolve_wish_list_id("https://amzn.asia/d/unknown", &fetcher).is_err());
+        assert!(resolve_wish_list_id("https://example.com/", &fetcher).is_err());
+    }
+
+    #[tokio::test]
+    async fn it_works_add_and_list_wish_list() {
+        let test = TestContext::new(Scraper::default());
+        let ctx = test.ctx();
+
+        let snapshot = add_wish_list(
+            &ctx,
+            "https://www.amazon.jp/hz/wishlist/ls/2BDAPI9RQ09E9?ref_=wl_share",
+        )
+        .await
+        .unwrap();
+        assert_eq!(snapshot.id, "2BDAPI9RQ09E9");
+
+        let actual = list_wish_lists(ctx.client().await.unwrap()).await.unwrap();
+        let found = actual.iter().find(|x| x.id == snapshot.id).unwrap();
+        assert_eq!(found.items, snapshot.items.len());
+        assert_eq!(found.scraped_at, snapshot.scraped_at);
+    }
+
     #[tokio::test]
     async fn it_works_update_all_wish_list() {
-        dotenv::dotenv().ok();
+        let test = TestContext::new(Scraper::default());
+        let ctx = test.ctx();
 
-        update_all_wish_list().await.unwrap();
+        update_all_wish_list(&ctx, &Shutdown::never())
+            .await
+            .unwrap();
     }
 }
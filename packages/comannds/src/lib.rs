@@ -1,4 +1,8 @@
 #[macro_use]
 extern crate log;
 
+pub mod config;
+pub mod context;
 pub mod domains;
+pub mod locale;
+pub mod shutdown;
@@ -0,0 +1,351 @@
+use chrono::{DateTime, FixedOffset};
+use std::fmt::Display;
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
+pub enum Locale {
+    #[default]
+    Ja,
+    En,
+}
+
+impl Locale {
+    pub fn parse<T: AsRef<str>>(value: T) -> Option<Locale> {
+        let value = value.as_ref().trim().to_lowercase();
+        match value.split(['_', '-', '.']).next()? {
+            "ja" => Some(Locale::Ja),
+            "en" => Some(Locale::En),
+            _ => None,
+        }
+    }
+
+    pub fn yen(&self, amount: f64) -> String {
+        let rounded = amount.round() as i64;
+        let digits = rounded.abs().to_string();
+        let mut grouped = String::new();
+        for (i, c) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i) % 3 == 0 {
+                grouped.push(',');
+            }
+            grouped.push(c);
+        }
+        let sign = if rounded < 0 { "-" } else { "" };
+        match self {
+            Locale::Ja => format!("{}{}円", sign, grouped),
+            Locale::En => format!("{}¥{}", sign, grouped),
+        }
+    }
+
+    pub fn datetime(&self, datetime: &DateTime<FixedOffset>) -> String {
+        let format = match self {
+            Locale::Ja => "%Y/%m/%d %H:%M:%S %Z",
+            Locale::En => "%b %d, %Y %I:%M %p %Z",
+        };
+        datetime.format(format).to_string()
+    }
+
+    pub fn sale_title(&self) -> &'static str {
+        match self {
+            Locale::Ja => "{{ wish_list }} のセール情報{% if rule %} ({{ rule }}){% endif %}",
+            Locale::En => "Sales in {{ wish_list }}{% if rule %} ({{ rule }}){% endif %}",
+        }
+    }
+
+    pub fn field_names(&self) -> [&'static str; 4] {
+        match self {
+            Locale::Ja => ["金額", "値引き率", "ポイント還元率", "更新日"],
+            Locale::En => ["Price", "Discount", "Points", "Updated"],
+        }
+    }
+
+    pub fn digest(&self, content: &str) -> String {
+        match self {
+            Locale::Ja => format!("{} (まとめ)", content),
+            Locale::En => format!("{} (digest)", content),
+        }
+    }
+
+    pub fn snap_failed<T: Display>(&self, id: &str, error: T) -> String {
+        match self {
+            Locale::Ja => format!("電子書籍の取得に失敗しました id: {}, error: {}", id, error),
+            Locale::En => format!("failed to snap ebook id: {}, error: {}", id, error),
+        }
+    }
+
+    pub fn no_messages(&self, title: &str) -> String {
+        match self {
+            Locale::Ja => format!("{} に通知するセール情報はありません", title),
+            Locale::En => format!("no messages for {}", title),
+        }
+    }
+
+    pub fn no_matches(&self, title: &str, rule: &str) -> String {
+        match self {
+            Locale::Ja => format!(
+                "{} にルール {} に一致するセール情報はありません",
+                title, rule
+            ),
+            Locale::En => format!("no matches for {} by rule {}", title, rule),
+        }
+    }
+
+    pub fn queued(&self, count: usize, content: &str) -> String {
+        match self {
+            Locale::Ja => format!("{} 件を配信待ちにしました: {}", count, content),
+            Locale::En => format!("queued {} items for {}", count, content),
+        }
+    }
+
+    pub fn waiting(&self, count: usize) -> String {
+        match self {
+            Locale::Ja => format!("{} 件の通知が配信待ちです", count),
+            Locale::En => format!("{} queued notifications are waiting", count),
+        }
+    }
+
+    pub fn archived(&self, count: i64) -> String {
+        match self {
+            Locale::Ja => format!(
+                "どこからも参照されていない {} 冊をアーカイブしました",
+                count
+            ),
+            Locale::En => format!("archived {} orphaned ebooks", count),
+        }
+    }
+
+    pub fn fresh_skipped(&self, count: usize) -> String {
+        match self {
+            Locale::Ja => format!("最近取得済みの {} 冊はスキップします", count),
+            Locale::En => format!("skipped {} recently snapped ebooks", count),
+        }
+    }
+
+    pub fn purged(&self, ebooks: i64, snapshots: i64) -> String {
+        match self {
+            Locale::Ja => format!(
+                "アーカイブ済みの {} 冊とスナップショット {} 件を削除しました",
+                ebooks, snapshots
+            ),
+            Locale::En => format!(
+                "purged {} archived ebooks and {} snapshots",
+                ebooks, snapshots
+            ),
+        }
+    }
+
+    pub fn history_headers(&self) -> [&'static str; 5] {
+        match self {
+            Locale::Ja => ["取得日時", "金額", "値引き率", "ポイント還元率", "実質価格"],
+            Locale::En => ["Scraped at", "Price", "Discount", "Points", "Effective"],
+        }
+    }
+
+    pub fn price_range(&self, lowest: f64, highest: f64, latest: f64) -> String {
+        match self {
+            Locale::Ja => format!(
+                "最安 {} / 最高 {} / 現在 {}",
+                self.yen(lowest),
+                self.yen(highest),
+                self.yen(latest)
+            ),
+            Locale::En => format!(
+                "lowest {} / highest {} / latest {}",
+                self.yen(lowest),
+                self.yen(highest),
+                self.yen(latest)
+            ),
+        }
+    }
+
+    pub fn started<T: Display>(&self, command: T) -> String {
+        match self {
+            Locale::Ja => format!("{} : 開始", command),
+            Locale::En => format!("{} : start", command),
+        }
+    }
+
+    pub fn finished<T: Display>(&self, command: T) -> String {
+        match self {
+            Locale::Ja => format!("{} : 終了", command),
+            Locale::En => format!("{} : finish", command),
+        }
+    }
+
+    pub fn skipped<T: Display>(&self, command: T) -> String {
+        match self {
+            Locale::Ja => format!("{} : 他で実行中のため見送り", command),
+            Locale::En => format!("{} : skipped, running elsewhere", command),
+        }
+    }
+
+    pub fn still_running<T: Display>(&self, command: T) -> String {
+        match self {
+            Locale::Ja => format!("{} : 前回の実行が終わっていないため見送り", command),
+            Locale::En => format!("{} : still running, skipped", command),
+        }
+    }
+
+    pub fn job_summary(&self, succeeded: i32, failed: i32) -> String {
+        match self {
+            Locale::Ja => format!("成功 {} 件, 失敗 {} 件", succeeded, failed),
+            Locale::En => format!("{} succeeded, {} failed", succeeded, failed),
+        }
+    }
+
+    pub fn shutdown_requested(&self) -> &'static str {
+        match self {
+            Locale::Ja => "終了が要求されたので実行中のジョブを待ちます",
+            Locale::En => "shutdown requested, waiting for running jobs",
+        }
+    }
+
+    pub fn not_registered(&self, id: &str) -> String {
+        match self {
+            Locale::Ja => format!("{} は登録されていません", id),
+            Locale::En => format!("{} is not registered", id),
+        }
+    }
+
+    pub fn not_watched(&self, asin: &str) -> String {
+        match self {
+            Locale::Ja => format!("{} は監視していません", asin),
+            Locale::En => format!("{} is not watched", asin),
+        }
+    }
+
+    pub fn not_found(&self, id: &str) -> String {
+        match self {
+            Locale::Ja => format!("{} は見つかりません", id),
+            Locale::En => format!("{} is not found", id),
+        }
+    }
+
+    pub fn no_snapshots(&self, asin: &str) -> String {
+        match self {
+            Locale::Ja => format!("{} のスナップショットはありません", asin),
+            Locale::En => format!("{} has no snapshots", asin),
+        }
+    }
+
+    pub fn added(&self, count: usize) -> String {
+        match self {
+            Locale::Ja => format!("追加: {}", count),
+            Locale::En => format!("added: {}", count),
+        }
+    }
+
+    pub fn removed(&self, count: usize) -> String {
+        match self {
+            Locale::Ja => format!("削除: {}", count),
+            Locale::En => format!("removed: {}", count),
+        }
+    }
+
+    pub fn piled(&self, count: usize, average_days: f64, oldest: Option<(&str, i64)>) -> String {
+        let piled = match self {
+            Locale::Ja => format!("積読: {} 冊, 平均 {:.1} 日", count, average_days),
+            Locale::En => format!("piled: {}, average {:.1} days", count, average_days),
+        };
+        match (self, oldest) {
+            (_, None) => piled,
+            (Locale::Ja, Some((title, days))) => {
+                format!("{}, 最古 {} ({} 日)", piled, title, days)
+            }
+            (Locale::En, Some((title, days))) => {
+                format!("{}, oldest {} ({} days)", piled, title, days)
+            }
+        }
+    }
+
+    pub fn run_labels(&self) -> [&'static str; 9] {
+        match self {
+            Locale::Ja => [
+                "ID",
+                "ジョブ",
+                "状態",
+                "開始",
+                "終了",
+                "所要時間",
+                "成功",
+                "失敗",
+                "エラー",
+            ],
+            Locale::En => [
+                "id",
+                "job",
+                "status",
+                "started",
+                "finished",
+                "duration",
+                "succeeded",
+                "failed",
+                "error",
+            ],
+        }
+    }
+
+    pub fn import_headers(&self) -> [&'static str; 4] {
+        match self {
+            Locale::Ja => ["エンティティ", "追加", "更新", "スキップ"],
+            Locale::En => ["entity", "inserted", "updated", "skipped"],
+        }
+    }
+
+    pub fn archive_valid(&self, archive: &str) -> String {
+        match self {
+            Locale::Ja => format!("{} は取り込めます", archive),
+            Locale::En => format!("{} is valid", archive),
+        }
+    }
+
+    pub fn config_valid(&self) -> &'static str {
+        match self {
+            Locale::Ja => "設定に問題はありません",
+            Locale::En => "config is valid",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Locale::parse("ja"), Some(Locale::Ja));
+        assert_eq!(Locale::parse("ja_JP.UTF-8"), Some(Locale::Ja));
+        assert_eq!(Locale::parse("EN-us"), Some(Locale::En));
+        assert_eq!(Locale::parse("fr"), None);
+    }
+
+    #[test]
+    fn test_yen() {
+        assert_eq!(Locale::Ja.yen(42.0), "42円");
+        assert_eq!(Locale::Ja.yen(1234.0), "1,234円");
+        assert_eq!(Locale::En.yen(1234567.0), "¥1,234,567");
+        assert_eq!(Locale::En.yen(-100.0), "-¥100");
+        assert_eq!(Locale::En.yen(999.6), "¥1,000");
+    }
+
+    #[test]
+    fn test_datetime() {
+        let datetime = FixedOffset::east_opt(9 * 3600)
+            .unwrap()
+            .with_ymd_and_hms(2023, 3, 1, 21, 5, 0)
+            .unwrap();
+        assert_eq!(Locale::Ja.datetime(&datetime), "2023/03/01 21:05:00 +09:00");
+        assert_eq!(
+            Locale::En.datetime(&datetime),
+            "Mar 01, 2023 09:05 PM +09:00"
+        );
+    }
+
+    #[test]
+    fn test_piled() {
+        assert_eq!(
+            Locale::En.piled(3, 12.34, Some(("title", 40))),
+            "piled: 3, average 12.3 days, oldest title (40 days)"
+        );
+        assert_eq!(Locale::Ja.piled(0, 0.0, None), "積読: 0 冊, 平均 0.0 日");
+    }
+}
@@ -0,0 +1,46 @@
+use std::future::pending;
+use tokio::sync::watch;
+
+/// 停止要求を受け取ったら、まだ始めていない処理をスキップするためのフラグ
+#[derive(Debug, Clone)]
+pub struct Shutdown(watch::Receiver<bool>);
+
+impl Shutdown {
+    pub fn new() -> (watch::Sender<bool>, Shutdown) {
+        let (sender, receiver) = watch::channel(false);
+        (sender, Shutdown(receiver))
+    }
+
+    /// 一回だけ実行するコマンド用。停止要求は来ない
+    pub fn never() -> Shutdown {
+        Shutdown::new().1
+    }
+
+    pub fn is_requested(&self) -> bool {
+        *self.0.borrow()
+    }
+
+    pub async fn requested(&mut self) {
+        while !self.is_requested() {
+            if self.0.changed().await.is_err() {
+                pending::<()>().await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_shutdown() {
+        let (sender, mut shutdown) = Shutdown::new();
+        assert!(!shutdown.is_requested());
+
+        sender.send(true).unwrap();
+        shutdown.requested().await;
+        assert!(shutdown.is_requested());
+        assert!(!Shutdown::never().is_requested());
+    }
+}
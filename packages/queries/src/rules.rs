@@ -0,0 +1,8 @@
+use anyhow::Result;
+use db_client::prisma::notification_rule::Data as NotificationRuleData;
+use db_client::prisma::PrismaClient;
+
+pub async fn select_all_rules(client: &PrismaClient) -> Result<Vec<NotificationRuleData>> {
+    let rules = client.notification_rule().find_many(vec![]).exec().await?;
+    Ok(rules)
+}
@@ -1,17 +1,20 @@
 use anyhow::Result;
-use db_client::prisma;
 use db_client::prisma::ebook_snapshot::OrderByParam;
 use db_client::prisma::wish_list::Data as WishListData;
-use db_client::prisma::{ebook, ebook_in_wish_list, wish_list};
+use db_client::prisma::{ebook, ebook_in_wish_list, wish_list, PrismaClient};
 use prisma_client_rust::Direction::Desc;
 
-pub async fn select_all_with_snapshot() -> Result<Vec<WishListData>> {
-    let client = prisma::new_client().await?;
+pub async fn select_all_with_snapshot(client: &PrismaClient) -> Result<Vec<WishListData>> {
     let wish_lists = client
         .wish_list()
         .find_many(vec![])
+        .with(wish_list::sale_threshold::fetch())
+        .with(wish_list::destinations::fetch(vec![]))
         .with(
-            wish_list::ebook_in_wish_list::fetch(vec![]).with(
+            wish_list::ebook_in_wish_list::fetch(vec![ebook_in_wish_list::removed_at::equals(
+                None,
+            )])
+            .with(
                 ebook_in_wish_list::ebook::fetch()
                     .with(ebook::snapshots::fetch(vec![]).order_by(OrderByParam::ScrapedAt(Desc))),
             ),
@@ -0,0 +1,266 @@
+use std::fmt;
+use std::str::FromStr;
+use url::Url;
+
+// この直後のセグメントが ASIN になる
+const ASIN_MARKERS: [&str; 5] = ["dp", "product", "d", "ASIN", "obidos"];
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct InvalidAsin(String);
+
+impl fmt::Display for InvalidAsin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} is not an ASIN", self.0)
+    }
+}
+
+impl std::error::Error for InvalidAsin {}
+
+/// Amazon の商品 ID。紙の本は ISBN-10 がそのまま ASIN になる
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone)]
+pub struct Asin(String);
+
+fn is_isbn10(value: &str) -> bool {
+    let chars = value.chars().collect::<Vec<_>>();
+    if chars.len() != 10 {
+        return false;
+    }
+    let digits = chars
+        .iter()
+        .enumerate()
+        .map(|(i, c)| match c {
+            'X' if i == 9 => Some(10),
+            c => c.to_digit(10),
+        })
+        .collect::<Option<Vec<_>>>();
+    let Some(digits) = digits else {
+        return false;
+    };
+    let sum = digits
+        .iter()
+        .enumerate()
+        .map(|(i, d)| (10 - i as u32) * d)
+        .sum::<u32>();
+    sum % 11 == 0
+}
+
+fn is_isbn13(value: &str) -> bool {
+    if value.len() != 13 || !value.chars().all(|c| c.is_ascii_digit()) {
+        return false;
+    }
+    let sum = value
+        .chars()
+        .filter_map(|c| c.to_digit(10))
+        .enumerate()
+        .map(|(i, d)| if i % 2 == 0 { d } else { d * 3 })
+        .sum::<u32>();
+    sum % 10 == 0
+}
+
+fn isbn10_check_digit(body: &str) -> char {
+    let sum = body
+        .chars()
+        .filter_map(|c| c.to_digit(10))
+        .enumerate()
+        .map(|(i, d)| (10 - i as u32) * d)
+        .sum::<u32>();
+    match (11 - sum % 11) % 11 {
+        10 => 'X',
+        d => char::from_digit(d, 10).unwrap(),
+    }
+}
+
+fn isbn13_check_digit(body: &str) -> char {
+    let sum = body
+        .chars()
+        .filter_map(|c| c.to_digit(10))
+        .enumerate()
+        .map(|(i, d)| if i % 2 == 0 { d } else { d * 3 })
+        .sum::<u32>();
+    char::from_digit((10 - sum % 10) % 10, 10).unwrap()
+}
+
+fn normalize(value: &str) -> String {
+    value
+        .trim()
+        .chars()
+        .filter(|c| *c != '-' && *c != ' ')
+        .collect::<String>()
+        .to_ascii_uppercase()
+}
+
+impl Asin {
+    /// B から始まる 10 桁か、ISBN-10 を受け付ける
+    pub fn parse(value: &str) -> Result<Asin, InvalidAsin> {
+        let asin = value.trim().to_ascii_uppercase();
+        let is_product = asin.len() == 10
+            && asin.starts_with('B')
+            && asin.chars().all(|c| c.is_ascii_alphanumeric());
+        if is_product || is_isbn10(asin.as_str()) {
+            return Ok(Asin(asin));
+        }
+        Err(InvalidAsin(value.to_string()))
+    }
+
+    /// ISBN-10 / ISBN-13 (978) から ASIN を作る。979 始まりは ASIN にならない
+    pub fn from_isbn(value: &str) -> Result<Asin, InvalidAsin> {
+        let isbn = normalize(value);
+        if is_isbn10(isbn.as_str()) {
+            return Ok(Asin(isbn));
+        }
+        if is_isbn13(isbn.as_str()) && isbn.starts_with("978") {
+            let body = &isbn[3..12];
+            return Ok(Asin(format!("{}{}", body, isbn10_check_digit(body))));
+        }
+        Err(InvalidAsin(value.to_string()))
+    }
+
+    /// /dp/ASIN, /<slug>/dp/ASIN, /gp/product/ASIN, /gp/aw/d/ASIN などから取り出す
+    pub fn from_url(url: &Url) -> Option<Asin> {
+        let segments = url
+            .path_segments()?
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>();
+        let by_path = segments
+            .windows(2)
+            .filter(|pair| ASIN_MARKERS.contains(&pair[0]))
+            .find_map(|pair| Asin::parse(pair[1]).ok());
+        by_path.or_else(|| {
+            url.query_pairs()
+                .filter(|(key, _)| key.eq_ignore_ascii_case("asin"))
+                .find_map(|(_, value)| Asin::parse(&value).ok())
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+
+    pub fn is_isbn(&self) -> bool {
+        is_isbn10(self.as_str())
+    }
+
+    pub fn to_isbn13(&self) -> Option<String> {
+        if !self.is_isbn() {
+            return None;
+        }
+        let body = format!("978{}", &self.0[..9]);
+        let check = isbn13_check_digit(body.as_str());
+        Some(format!("{}{}", body, check))
+    }
+
+    pub fn url(&self) -> Url {
+        Url::parse("https://www.amazon.co.jp/dp/")
+            .and_then(|url| url.join(self.as_str()))
+            .unwrap()
+    }
+}
+
+impl FromStr for Asin {
+    type Err = InvalidAsin;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Asin::parse(s)
+    }
+}
+
+impl fmt::Display for Asin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn asin(value: &str) -> Asin {
+        Asin::parse(value).unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(asin("B09RQGMYKZ").as_str(), "B09RQGMYKZ");
+        assert_eq!(asin(" b09rqgmykz ").as_str(), "B09RQGMYKZ");
+        assert_eq!(asin("4063843750").as_str(), "4063843750");
+        assert_eq!(asin("480611152X").as_str(), "480611152X");
+        assert!(Asin::parse("2BDAPI9RQ09E9").is_err());
+        assert!(Asin::parse("4063843757").is_err());
+        assert!(Asin::parse("B09RQ-MYKZ").is_err());
+        assert!(Asin::parse("").is_err());
+    }
+
+    #[test]
+    fn test_from_url() {
+        let expected = Some(asin("B09RQGMYKZ"));
+        for url in [
+            "https://www.amazon.co.jp/dp/B09RQGMYKZ/?coliid=IH",
+            "https://www.amazon.co.jp/%E3%82%BF%E3%82%A4%E3%83%88%E3%83%AB/dp/B09RQGMYKZ/ref=sr_1_1",
+            "https://www.amazon.co.jp/gp/product/B09RQGMYKZ",
+            "https://www.amazon.co.jp/gp/aw/d/B09RQGMYKZ",
+            "https://www.amazon.co.jp/exec/obidos/ASIN/B09RQGMYKZ/",
+            "https://www.amazon.co.jp/gp/offer-listing?asin=B09RQGMYKZ",
+        ] {
+            assert_eq!(Asin::from_url(&Url::parse(url).unwrap()), expected, "{}", url);
+        }
+        assert_eq!(
+            Asin::from_url(
+                &Url::parse("https://www.amazon.co.jp/hz/wishlist/ls/2BDAPI9RQ09E9").unwrap()
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_isbn() {
+        assert_eq!(
+            Asin::from_isbn("978-4-06-384375-0").unwrap(),
+            asin("4063843750")
+        );
+        assert_eq!(
+            Asin::from_isbn("4-06-384375-0").unwrap(),
+            asin("4063843750")
+        );
+        assert_eq!(
+            Asin::from_isbn("9784806111528").unwrap(),
+            asin("480611152X")
+        );
+        assert!(Asin::from_isbn("9791032305690").is_err());
+        assert!(Asin::from_isbn("9784063843751").is_err());
+        assert_eq!(asin("4063843750").to_isbn13().unwrap(), "9784063843750");
+        assert_eq!(asin("B09RQGMYKZ").to_isbn13(), None);
+    }
+
+    #[test]
+    fn test_url() {
+        assert_eq!(
+            asin("B09RQGMYKZ").url(),
+            Url::parse("https://www.amazon.co.jp/dp/B09RQGMYKZ").unwrap()
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn doesnt_crash(value: String) {
+            let _ = Asin::parse(&value);
+            let _ = Asin::from_isbn(&value);
+        }
+
+        #[test]
+        fn isbn_roundtrip(body in "[0-9]{9}") {
+            let isbn10 = format!("{}{}", body, isbn10_check_digit(&body));
+            let asin = Asin::from_isbn(&isbn10).unwrap();
+            let isbn13 = asin.to_isbn13().unwrap();
+            prop_assert_eq!(Asin::from_isbn(&isbn13).unwrap(), asin);
+        }
+
+        #[test]
+        fn extracts_from_url(id in "B[0-9A-Z]{9}", slug in "[a-z-]{1,12}") {
+            let expected = Some(Asin(id.clone()));
+            let url = Url::parse(&format!("https://www.amazon.co.jp/{}/dp/{}/ref=x", slug, id)).unwrap();
+            prop_assert_eq!(Asin::from_url(&url), expected.clone());
+            prop_assert_eq!(Asin::from_url(&Asin(id).url()), expected);
+        }
+    }
+}
@@ -1 +1,3 @@
+pub mod asin;
 pub mod dict;
+pub mod wish_list_url;
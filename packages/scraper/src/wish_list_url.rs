@@ -0,0 +1,212 @@
+use crate::asin::Asin;
+use url::Url;
+
+const AMAZON_DOMAINS: [&str; 16] = [
+    "amazon.co.jp",
+    "amazon.jp",
+    "amazon.com",
+    "amazon.co.uk",
+    "amazon.de",
+    "amazon.fr",
+    "amazon.it",
+    "amazon.es",
+    "amazon.nl",
+    "amazon.ca",
+    "amazon.com.mx",
+    "amazon.com.br",
+    "amazon.com.au",
+    "amazon.in",
+    "amazon.sg",
+    "amazon.cn",
+];
+const SHORT_LINK_HOSTS: [&str; 3] = ["amzn.asia", "amzn.to", "a.co"];
+// この直後のセグメントがウィッシュリストの ID になる
+const ID_MARKERS: [&str; 4] = ["ls", "wishlist", "genericItemsPage", "invite"];
+const ID_QUERY_KEYS: [&str; 2] = ["lid", "listId"];
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum WishListRef {
+    Id(String),
+    /// amzn.asia などの短縮 URL。リダイレクト先を開いてから改めて parse する
+    ShortLink(Url),
+}
+
+/// 商品の ASIN は ウィッシュリストの ID として扱わない
+fn is_id(value: &str) -> bool {
+    (10..=20).contains(&value.len())
+        && value.chars().all(|c| c.is_ascii_alphanumeric())
+        && Asin::parse(value).is_err()
+}
+
+/// amazon.evil.com のような似た名前のホストは受け付けない
+fn is_amazon(host: &str) -> bool {
+    AMAZON_DOMAINS
+        .iter()
+        .any(|domain| host == *domain || host.ends_with(format!(".{}", domain).as_str()))
+}
+
+fn to_url(value: &str) -> Option<Url> {
+    if value.contains("://") {
+        return Url::parse(value).ok();
+    }
+    if value.contains('/') || value.contains('.') {
+        return Url::parse(format!("https://{}", value).as_str()).ok();
+    }
+    None
+}
+
+fn id_from_url(url: &Url) -> Option<String> {
+    let segments = url
+        .path_segments()?
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>();
+    let by_path = segments
+        .windows(2)
+        .find(|pair| {
+            ID_MARKERS.contains(&pair[0]) && !ID_MARKERS.contains(&pair[1]) && is_id(pair[1])
+        })
+        .map(|pair| pair[1].to_string());
+    let by_query = || {
+        url.query_pairs()
+            .find(|(key, value)| ID_QUERY_KEYS.contains(&key.as_ref()) && is_id(value))
+            .map(|(_, value)| value.to_string())
+    };
+    by_path.or_else(by_query)
+}
+
+/// ウィッシュリストの ID や、貼り付けられた各種 URL から ID を取り出す
+pub fn parse(value: &str) -> Option<WishListRef> {
+    let value = value.trim();
+    if is_id(value) {
+        return Some(WishListRef::Id(value.to_ascii_uppercase()));
+    }
+
+    let url = to_url(value)?;
+    let host = url
+        .host_str()?
+        .trim_start_matches("www.")
+        .to_ascii_lowercase();
+    if SHORT_LINK_HOSTS.contains(&host.as_str()) {
+        return Some(WishListRef::ShortLink(url));
+    }
+    if !is_amazon(host.as_str()) {
+        return None;
+    }
+    id_from_url(&url).map(|id| WishListRef::Id(id.to_ascii_uppercase()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn id(value: &str) -> Option<WishListRef> {
+        Some(WishListRef::Id(value.to_string()))
+    }
+
+    #[test]
+    fn test_parse() {
+        let expected = id("2BDAPI9RQ09E9");
+        assert_eq!(parse("2BDAPI9RQ09E9"), expected);
+        assert_eq!(parse(" 2bdapi9rq09e9 "), expected);
+        assert_eq!(
+            parse("https://www.amazon.jp/hz/wishlist/ls/2BDAPI9RQ09E9"),
+            expected
+        );
+        assert_eq!(
+            parse("amazon.co.jp/hz/wishlist/ls/2BDAPI9RQ09E9?ref_=wl_share"),
+            expected
+        );
+        assert_eq!(
+            parse("https://www.amazon.co.jp/registry/wishlist/2BDAPI9RQ09E9/ref=cm_wl_huc_view"),
+            expected
+        );
+        assert_eq!(
+            parse("https://www.amazon.co.jp/gp/registry/wishlist/2BDAPI9RQ09E9"),
+            expected
+        );
+        assert_eq!(
+            parse(
+                "https://www.amazon.co.jp/hz/wishlist/genericItemsPage/2BDAPI9RQ09E9?type=wishlist"
+            ),
+            expected
+        );
+        assert_eq!(
+            parse("https://www.amazon.co.jp/hz/wishlist/ls?lid=2BDAPI9RQ09E9&ref_=wl_share"),
+            expected
+        );
+        assert_eq!(
+            parse("https://amzn.asia/d/abcdEFG"),
+            Some(WishListRef::ShortLink(
+                Url::parse("https://amzn.asia/d/abcdEFG").unwrap()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects() {
+        assert_eq!(parse(""), None);
+        assert_eq!(parse("2BDAPI9RQ09E9; drop"), None);
+        assert_eq!(parse("B09RQGMYKZ"), None);
+        assert_eq!(parse("4088725093"), None);
+        assert_eq!(parse("https://www.amazon.co.jp/dp/B09RQGMYKZ"), None);
+        assert_eq!(
+            parse("https://example.com/hz/wishlist/ls/2BDAPI9RQ09E9"),
+            None
+        );
+        assert_eq!(
+            parse("https://amazon.evil.com/hz/wishlist/ls/2BDAPI9RQ09E9"),
+            None
+        );
+        assert_eq!(
+            parse("https://www.amazon.co.jp.evil.com/hz/wishlist/ls/2BDAPI9RQ09E9"),
+            None
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn doesnt_crash(value: String) {
+            parse(&value);
+        }
+
+        #[test]
+        fn normalizes_url_forms(
+            id in "[A-Z0-9]{10,16}",
+            host in "(www\\.)?amazon\\.(co\\.jp|jp|com)",
+            reference in "[a-z_]{0,12}",
+        ) {
+            prop_assume!(Asin::parse(&id).is_err());
+            let expected = Some(WishListRef::Id(id.clone()));
+            let forms = [
+                id.to_ascii_lowercase(),
+                format!("https://{}/hz/wishlist/ls/{}", host, id),
+                format!("{}/hz/wishlist/ls/{}/?ref_={}", host, id, reference),
+                format!("https://{}/registry/wishlist/{}/ref={}", host, id, reference),
+                format!("https://{}/hz/wishlist/ls?lid={}&ref_={}", host, id, reference),
+            ];
+            for form in forms {
+                prop_assert_eq!(parse(&form), expected.clone(), "{}", form);
+            }
+        }
+
+        #[test]
+        fn rejects_asins(
+            asin in "B[A-Z0-9]{9}",
+            host in "(www\\.)?amazon\\.(co\\.jp|jp|com)",
+        ) {
+            prop_assert_eq!(parse(&asin), None);
+            let url = format!("https://{}/hz/wishlist/ls/{}", host, asin);
+            prop_assert_eq!(parse(&url), None, "{}", url);
+        }
+
+        #[test]
+        fn rejects_lookalike_hosts(
+            id in "[A-Z0-9]{13}",
+            host in "amazon\\.[a-z]{4,8}\\.com|[a-z]{1,8}amazon\\.com|amazon\\.co\\.jp\\.[a-z]{1,8}",
+        ) {
+            let url = format!("https://{}/hz/wishlist/ls/{}", host, id);
+            prop_assert_eq!(parse(&url), None, "{}", url);
+        }
+    }
+}
@@ -0,0 +1,16 @@
+-- CreateTable
+CREATE TABLE "SaleThreshold" (
+    "wishListId" TEXT NOT NULL,
+    "discountLow" DOUBLE PRECISION,
+    "discountMiddle" DOUBLE PRECISION,
+    "discountHigh" DOUBLE PRECISION,
+    "pointsLow" DOUBLE PRECISION,
+    "pointsMiddle" DOUBLE PRECISION,
+    "pointsHigh" DOUBLE PRECISION,
+    "priceUnder" DOUBLE PRECISION,
+
+    CONSTRAINT "SaleThreshold_pkey" PRIMARY KEY ("wishListId")
+);
+
+-- AddForeignKey
+ALTER TABLE "SaleThreshold" ADD CONSTRAINT "SaleThreshold_wishListId_fkey" FOREIGN KEY ("wishListId") REFERENCES "WishList"("id") ON DELETE CASCADE ON UPDATE CASCADE;
@@ -0,0 +1,17 @@
+-- CreateTable
+CREATE TABLE "NotificationRule" (
+    "id" TEXT NOT NULL,
+    "name" TEXT NOT NULL,
+    "expression" TEXT NOT NULL,
+    "channel" TEXT,
+    "userId" TEXT,
+    "wishListId" TEXT,
+
+    CONSTRAINT "NotificationRule_pkey" PRIMARY KEY ("id")
+);
+
+-- AddForeignKey
+ALTER TABLE "NotificationRule" ADD CONSTRAINT "NotificationRule_userId_fkey" FOREIGN KEY ("userId") REFERENCES "User"("id") ON DELETE CASCADE ON UPDATE CASCADE;
+
+-- AddForeignKey
+ALTER TABLE "NotificationRule" ADD CONSTRAINT "NotificationRule_wishListId_fkey" FOREIGN KEY ("wishListId") REFERENCES "WishList"("id") ON DELETE CASCADE ON UPDATE CASCADE;
@@ -0,0 +1,14 @@
+-- CreateTable
+CREATE TABLE "WishListDestination" (
+    "id" TEXT NOT NULL,
+    "wishListId" TEXT NOT NULL,
+    "destination" TEXT NOT NULL,
+
+    CONSTRAINT "WishListDestination_pkey" PRIMARY KEY ("id")
+);
+
+-- CreateIndex
+CREATE UNIQUE INDEX "WishListDestination_wishListId_destination_key" ON "WishListDestination"("wishListId", "destination");
+
+-- AddForeignKey
+ALTER TABLE "WishListDestination" ADD CONSTRAINT "WishListDestination_wishListId_fkey" FOREIGN KEY ("wishListId") REFERENCES "WishList"("id") ON DELETE CASCADE ON UPDATE CASCADE;
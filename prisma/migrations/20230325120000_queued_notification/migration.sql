@@ -0,0 +1,11 @@
+-- CreateTable
+CREATE TABLE "QueuedNotification" (
+    "id" TEXT NOT NULL,
+    "destination" TEXT NOT NULL,
+    "content" TEXT NOT NULL,
+    "embed" TEXT NOT NULL,
+    "queuedAt" BIGINT NOT NULL,
+    "deliveredAt" BIGINT,
+
+    CONSTRAINT "QueuedNotification_pkey" PRIMARY KEY ("id")
+);
@@ -0,0 +1,10 @@
+-- CreateTable
+CREATE TABLE "WatchedEbook" (
+    "ebookId" TEXT NOT NULL,
+    "watchedAt" BIGINT NOT NULL,
+
+    CONSTRAINT "WatchedEbook_pkey" PRIMARY KEY ("ebookId")
+);
+
+-- AddForeignKey
+ALTER TABLE "WatchedEbook" ADD CONSTRAINT "WatchedEbook_ebookId_fkey" FOREIGN KEY ("ebookId") REFERENCES "Ebook"("id") ON DELETE CASCADE ON UPDATE CASCADE;
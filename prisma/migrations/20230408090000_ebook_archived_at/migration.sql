@@ -0,0 +1,2 @@
+-- AlterTable
+ALTER TABLE "Ebook" ADD COLUMN     "archivedAt" BIGINT;
@@ -0,0 +1,19 @@
+-- AlterTable
+ALTER TABLE "EbookInWishList" DROP CONSTRAINT "EbookInWishList_pkey",
+ADD COLUMN     "id" TEXT,
+ADD COLUMN     "addedAt" BIGINT,
+ADD COLUMN     "removedAt" BIGINT;
+
+-- 既存の行は ID を振り、追加日時はウィッシュリストの取得日時で埋める
+UPDATE "EbookInWishList" AS e
+SET "id" = md5(random()::text || clock_timestamp()::text || e."wishListId" || e."ebookId"),
+    "addedAt" = w."scrapedAt"
+FROM "WishList" AS w
+WHERE w."id" = e."wishListId";
+
+ALTER TABLE "EbookInWishList" ALTER COLUMN "id" SET NOT NULL,
+ALTER COLUMN "addedAt" SET NOT NULL,
+ADD CONSTRAINT "EbookInWishList_pkey" PRIMARY KEY ("id");
+
+-- CreateIndex
+CREATE INDEX "EbookInWishList_wishListId_ebookId_idx" ON "EbookInWishList"("wishListId", "ebookId");
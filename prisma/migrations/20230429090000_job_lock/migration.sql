@@ -0,0 +1,9 @@
+-- CreateTable
+CREATE TABLE "JobLock" (
+    "job" TEXT NOT NULL,
+    "owner" TEXT NOT NULL,
+    "acquiredAt" BIGINT NOT NULL,
+    "expiresAt" BIGINT NOT NULL,
+
+    CONSTRAINT "JobLock_pkey" PRIMARY KEY ("job")
+);
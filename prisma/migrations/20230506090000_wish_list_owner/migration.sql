@@ -0,0 +1,5 @@
+-- AlterTable
+ALTER TABLE "WishList" ADD COLUMN     "userId" TEXT;
+
+-- AddForeignKey
+ALTER TABLE "WishList" ADD CONSTRAINT "WishList_userId_fkey" FOREIGN KEY ("userId") REFERENCES "User"("id") ON DELETE SET NULL ON UPDATE CASCADE;
@@ -0,0 +1,12 @@
+-- 同時に取得したスナップショットで重複した、外されていない行は古い方だけ残す
+DELETE FROM "EbookInWishList" AS e
+USING "EbookInWishList" AS o
+WHERE e."removedAt" IS NULL
+  AND o."removedAt" IS NULL
+  AND o."wishListId" = e."wishListId"
+  AND o."ebookId" = e."ebookId"
+  AND (o."addedAt", o."id") < (e."addedAt", e."id");
+
+-- CreateIndex
+-- 部分インデックスは schema.prisma で表せないので migration にだけ書く
+CREATE UNIQUE INDEX "EbookInWishList_wishListId_ebookId_active_key" ON "EbookInWishList"("wishListId", "ebookId") WHERE "removedAt" IS NULL;